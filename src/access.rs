@@ -0,0 +1,403 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{
+        agent::{Agent, AgentType},
+        asset::Asset,
+        space::SpaceType,
+        Relation,
+    },
+    spatial::SpatialTree,
+    tenant::Tenant,
+};
+
+/// Door or zone a person can open, and why
+#[derive(Serialize, Debug, Clone)]
+pub struct Grant {
+    pub target: Relation,
+    /// Zone including the door, if the door is granted through a zone
+    pub zone: Option<Relation>,
+    /// Agents from the one the person is a member of
+    /// to the access group granting `target`
+    pub path: Vec<Relation>,
+}
+
+/// Every door and zone a person can open
+#[derive(Serialize, Debug, Clone)]
+pub struct PersonAccess {
+    pub person: Relation,
+    pub doors: Vec<Grant>,
+    pub zones: Vec<Grant>,
+}
+
+impl PersonAccess {
+    /// Whether the person can open the door or zone
+    pub fn can_open(&self, id: &Uuid) -> bool {
+        self.doors
+            .iter()
+            .chain(&self.zones)
+            .any(|grant| grant.target.id == *id)
+    }
+}
+
+/// Resolves doors and zones granted to persons by access groups
+///
+/// Membership follows `member_of` and `has_member` alike, through
+/// any number of nested agents. A group including a zone grants
+/// the doors located in the zone, or in any space within it.
+#[derive(Debug, Clone, Default)]
+pub struct AccessResolver {
+    agents: HashMap<Uuid, Agent>,
+    /// Agents each agent is a member of
+    member_of: HashMap<Uuid, BTreeSet<Uuid>>,
+    doors: HashMap<Uuid, Relation>,
+    zones: HashMap<Uuid, Relation>,
+    group_doors: HashMap<Uuid, BTreeSet<Uuid>>,
+    group_zones: HashMap<Uuid, BTreeSet<Uuid>>,
+    zone_doors: HashMap<Uuid, BTreeSet<Uuid>>,
+}
+
+impl AccessResolver {
+    /// Load the tenant and build the resolver
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+
+        Ok(Self::new(&tenant))
+    }
+
+    pub fn new(tenant: &Tenant) -> Self {
+        let tree = SpatialTree::new(tenant.spaces().cloned().collect());
+
+        let agents: HashMap<Uuid, Agent> = tenant
+            .agents()
+            .map(|agent| (agent.id(), agent.clone()))
+            .collect();
+
+        let doors: HashMap<Uuid, &Asset> = tenant
+            .assets()
+            .filter(|asset| matches!(asset, Asset::Door(_)))
+            .map(|asset| (asset.id(), asset))
+            .collect();
+
+        let zones: HashMap<Uuid, Relation> = tree
+            .spaces()
+            .filter(|space| space.space_type() == SpaceType::AccessControlZone)
+            .map(|space| (space.id(), Relation::to(space)))
+            .collect();
+
+        let mut member_of: HashMap<Uuid, BTreeSet<Uuid>> = HashMap::new();
+        let mut group_doors: HashMap<Uuid, BTreeSet<Uuid>> = HashMap::new();
+        let mut group_zones: HashMap<Uuid, BTreeSet<Uuid>> = HashMap::new();
+
+        for agent in agents.values() {
+            let id = agent.id();
+            let (parents, members, included_doors, included_zones) = match agent {
+                Agent::AccessGroup(agent) => (
+                    &agent.member_of,
+                    &agent.has_member,
+                    &agent.includes_door,
+                    &agent.includes_zone,
+                ),
+                Agent::Company(agent) => (&agent.member_of, &agent.has_member, &None, &None),
+                Agent::Department(agent) => (&agent.member_of, &agent.has_member, &None, &None),
+                Agent::Person(agent) => (&agent.member_of, &None, &None, &None),
+            };
+
+            for parent in parents.iter().flatten() {
+                if agents.contains_key(&parent.id) {
+                    member_of.entry(id).or_default().insert(parent.id);
+                }
+            }
+
+            for member in members.iter().flatten() {
+                if agents.contains_key(&member.id) {
+                    member_of.entry(member.id).or_default().insert(id);
+                }
+            }
+
+            for door in included_doors.iter().flatten() {
+                if doors.contains_key(&door.id) {
+                    group_doors.entry(id).or_default().insert(door.id);
+                }
+            }
+
+            for zone in included_zones.iter().flatten() {
+                if zones.contains_key(&zone.id) {
+                    group_zones.entry(id).or_default().insert(zone.id);
+                }
+            }
+        }
+
+        let mut zone_doors: HashMap<Uuid, BTreeSet<Uuid>> = HashMap::new();
+
+        for (id, door) in &doors {
+            for relation in door.located_in() {
+                let mut spaces = vec![relation.id];
+                spaces.extend(tree.ancestors(&relation.id).iter().map(|space| space.id()));
+
+                for space in spaces {
+                    if zones.contains_key(&space) {
+                        zone_doors.entry(space).or_default().insert(*id);
+                    }
+                }
+            }
+        }
+
+        Self {
+            doors: doors
+                .iter()
+                .map(|(id, door)| (*id, Relation::to(*door)))
+                .collect(),
+            agents,
+            member_of,
+            zones,
+            group_doors,
+            group_zones,
+            zone_doors,
+        }
+    }
+
+    /// Doors and zones the person can open, `None` if
+    /// `person` is not a known person
+    ///
+    /// Each granting group is reported once, through the
+    /// shortest membership path leading to it.
+    pub fn access(&self, person: &Uuid) -> Option<PersonAccess> {
+        let agent = self.agents.get(person)?;
+
+        if agent.agent_type() != AgentType::Person {
+            return None;
+        }
+
+        let mut doors = Vec::new();
+        let mut zones = Vec::new();
+
+        let mut visited = HashSet::from([*person]);
+        let mut queue: VecDeque<(Uuid, Vec<Uuid>)> = VecDeque::from([(*person, Vec::new())]);
+
+        while let Some((id, path)) = queue.pop_front() {
+            let relations: Vec<Relation> = path
+                .iter()
+                .filter_map(|id| self.agents.get(id))
+                .map(Relation::to)
+                .collect();
+
+            for door in self.group_doors.get(&id).into_iter().flatten() {
+                doors.push(Grant {
+                    target: self.doors[door].clone(),
+                    zone: None,
+                    path: relations.clone(),
+                });
+            }
+
+            for zone in self.group_zones.get(&id).into_iter().flatten() {
+                zones.push(Grant {
+                    target: self.zones[zone].clone(),
+                    zone: None,
+                    path: relations.clone(),
+                });
+
+                for door in self.zone_doors.get(zone).into_iter().flatten() {
+                    doors.push(Grant {
+                        target: self.doors[door].clone(),
+                        zone: Some(self.zones[zone].clone()),
+                        path: relations.clone(),
+                    });
+                }
+            }
+
+            for parent in self.member_of.get(&id).into_iter().flatten() {
+                if visited.insert(*parent) {
+                    let mut path = path.clone();
+                    path.push(*parent);
+                    queue.push_back((*parent, path));
+                }
+            }
+        }
+
+        for grants in [&mut doors, &mut zones] {
+            grants.sort_by(|a, b| {
+                a.target
+                    .name
+                    .cmp(&b.target.name)
+                    .then(a.target.id.cmp(&b.target.id))
+                    .then(a.path.len().cmp(&b.path.len()))
+            });
+        }
+
+        Some(PersonAccess {
+            person: Relation::to(agent),
+            doors,
+            zones,
+        })
+    }
+
+    /// Every person able to open the door, with the grants allowing it
+    pub fn openers(&self, door: &Uuid) -> Vec<PersonAccess> {
+        let mut persons: Vec<&Agent> = self
+            .agents
+            .values()
+            .filter(|agent| agent.agent_type() == AgentType::Person)
+            .collect();
+        persons.sort_by(|a, b| a.name().cmp(b.name()).then(a.id().cmp(&b.id())));
+
+        persons
+            .into_iter()
+            .filter_map(|person| self.access(&person.id()))
+            .filter_map(|mut access| {
+                access.doors.retain(|grant| grant.target.id == *door);
+                access.zones.clear();
+
+                (!access.doors.is_empty()).then_some(access)
+            })
+            .collect()
+    }
+}
+
+fn display_name(relation: &Relation) -> String {
+    relation
+        .name
+        .clone()
+        .unwrap_or_else(|| relation.id.to_string())
+}
+
+impl fmt::Display for Grant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path: Vec<String> = self.path.iter().map(display_name).collect();
+
+        write!(f, "{} via {}", display_name(&self.target), path.join(" > "))?;
+
+        if let Some(zone) = &self.zone {
+            write!(f, " > zone {}", display_name(zone))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for PersonAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} ({})", display_name(&self.person), self.person.id)?;
+
+        for grant in &self.doors {
+            writeln!(f, "  door {grant}")?;
+        }
+
+        for grant in &self.zones {
+            writeln!(f, "  zone {grant}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::{space::Space, Entity};
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn agent(value: Value) -> Entity {
+        serde_json::from_value::<Agent>(value).unwrap().into()
+    }
+
+    fn resolver() -> AccessResolver {
+        AccessResolver::new(&Tenant::new(vec![
+            agent(
+                json!({"type": "person", "id": id(1), "name": "Jane", "memberOf": [{"id": id(2)}]}),
+            ),
+            agent(json!({"type": "person", "id": id(5), "name": "John"})),
+            agent(json!({"type": "department", "id": id(2), "name": "Operations"})),
+            agent(json!({
+                "type": "access_group",
+                "id": id(3),
+                "name": "Staff",
+                "hasMember": [{"id": id(2)}],
+                "memberOf": [{"id": id(4)}],
+                "includesDoor": [{"id": id(10)}],
+            })),
+            agent(json!({
+                "type": "access_group",
+                "id": id(4),
+                "name": "Everyone",
+                "memberOf": [{"id": id(3)}],
+                "includesZone": [{"id": id(20)}],
+            })),
+            serde_json::from_value::<Asset>(json!({"type": "door", "id": id(10), "name": "Front"}))
+                .unwrap()
+                .into(),
+            serde_json::from_value::<Asset>(json!({
+                "type": "door",
+                "id": id(11),
+                "name": "Lab",
+                "locatedIn": [{"id": id(21)}],
+            }))
+            .unwrap()
+            .into(),
+            serde_json::from_value::<Space>(json!({
+                "type": "access_control_zone",
+                "id": id(20),
+                "name": "Secure",
+                "hasPart": [{"id": id(21)}],
+            }))
+            .unwrap()
+            .into(),
+            serde_json::from_value::<Space>(json!({"type": "room", "id": id(21), "name": "Lab"}))
+                .unwrap()
+                .into(),
+        ]))
+    }
+
+    fn path(grant: &Grant) -> Vec<Uuid> {
+        grant.path.iter().map(|agent| agent.id).collect()
+    }
+
+    #[test]
+    fn membership_is_followed_both_ways_and_through_cycles() {
+        let access = resolver().access(&id(1)).unwrap();
+
+        assert!(access.can_open(&id(10)));
+        assert!(access.can_open(&id(20)));
+
+        let front = access.doors.iter().find(|grant| grant.target.id == id(10));
+        assert_eq!(path(front.unwrap()), [id(2), id(3)]);
+        assert_eq!(path(&access.zones[0]), [id(2), id(3), id(4)]);
+    }
+
+    #[test]
+    fn zones_grant_the_doors_within_them() {
+        let resolver = resolver();
+        let access = resolver.access(&id(1)).unwrap();
+
+        let lab = access.doors.iter().find(|grant| grant.target.id == id(11));
+        assert_eq!(lab.unwrap().zone.as_ref().map(|zone| zone.id), Some(id(20)));
+
+        let openers = resolver.openers(&id(11));
+        assert_eq!(openers.len(), 1);
+        assert_eq!(openers[0].person.id, id(1));
+        assert!(openers[0].zones.is_empty());
+    }
+
+    #[test]
+    fn only_persons_have_access() {
+        let resolver = resolver();
+
+        assert!(resolver.access(&id(3)).is_none());
+        assert!(resolver.access(&id(99)).is_none());
+        assert!(resolver.access(&id(5)).unwrap().doors.is_empty());
+    }
+}
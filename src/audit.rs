@@ -0,0 +1,320 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    access::AccessResolver,
+    error::Error,
+    handler::Handler,
+    models::{agent::Agent, space::Space, Entity, Relation, Resource},
+    tenant::Tenant,
+};
+
+/// Members, doors and zones of an access group
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupSnapshot {
+    pub group: Relation,
+    pub members: Vec<Relation>,
+    pub doors: Vec<Relation>,
+    pub zones: Vec<Relation>,
+}
+
+/// Door a person can open
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorGrant {
+    pub person: Relation,
+    pub door: Relation,
+}
+
+/// Access groups and the resolved door grants at a point in time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub groups: Vec<GroupSnapshot>,
+    /// Grants ordered by person, then door
+    pub grants: Vec<DoorGrant>,
+}
+
+impl AccessSnapshot {
+    /// Load the tenant and take a snapshot
+    pub async fn load(
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        taken_at: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+
+        Ok(Self::new(&tenant, taken_at))
+    }
+
+    pub fn new(tenant: &Tenant, taken_at: DateTime<Utc>) -> Self {
+        let resolver = AccessResolver::new(tenant);
+
+        let mut groups: Vec<GroupSnapshot> = tenant
+            .agents()
+            .filter_map(|agent| match agent {
+                Agent::AccessGroup(group) => Some(GroupSnapshot {
+                    group: Relation::to(agent),
+                    members: Self::linked(
+                        tenant,
+                        &group.id,
+                        &group.has_member,
+                        "member_of",
+                        |_| true,
+                    ),
+                    doors: group.includes_door.clone().unwrap_or_default(),
+                    zones: Self::linked(
+                        tenant,
+                        &group.id,
+                        &group.includes_zone,
+                        "included_in",
+                        |entity| matches!(entity, Entity::Space(Space::AccessControlZone(_))),
+                    ),
+                }),
+                _ => None,
+            })
+            .collect();
+        groups.sort_by_key(|group| group.group.id);
+
+        let mut grants: BTreeMap<(Uuid, Uuid), DoorGrant> = BTreeMap::new();
+
+        for agent in tenant.agents() {
+            let Some(access) = resolver.access(&agent.id()) else {
+                continue;
+            };
+
+            for grant in access.doors {
+                grants
+                    .entry((access.person.id, grant.target.id))
+                    .or_insert_with(|| DoorGrant {
+                        person: access.person.clone(),
+                        door: grant.target,
+                    });
+            }
+        }
+
+        Self {
+            taken_at,
+            groups,
+            grants: grants.into_values().collect(),
+        }
+    }
+
+    /// Relations of a group in `forward`, merged with the entities
+    /// pointing back at the group through their `inverse` field
+    fn linked(
+        tenant: &Tenant,
+        group: &Uuid,
+        forward: &Option<Vec<Relation>>,
+        inverse: &str,
+        target: fn(&Entity) -> bool,
+    ) -> Vec<Relation> {
+        let mut linked = forward.clone().unwrap_or_default();
+
+        for entity in tenant.entities().iter().filter(|entity| target(entity)) {
+            let points_back = entity.relations().into_iter().any(|(field, relations)| {
+                field == inverse && relations.iter().any(|relation| relation.id == *group)
+            });
+
+            if points_back && !linked.iter().any(|relation| relation.id == entity.id()) {
+                linked.push(Relation::to(entity));
+            }
+        }
+
+        linked
+    }
+
+    /// Write the snapshot as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Read a snapshot written by `save`
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Grants added and removed between this snapshot and `later`
+    pub fn diff(&self, later: &AccessSnapshot) -> AccessDiff {
+        let index = |snapshot: &AccessSnapshot| -> HashMap<(Uuid, Uuid), DoorGrant> {
+            snapshot
+                .grants
+                .iter()
+                .map(|grant| ((grant.person.id, grant.door.id), grant.clone()))
+                .collect()
+        };
+
+        let before = index(self);
+        let after = index(later);
+
+        let changes = |from: &HashMap<(Uuid, Uuid), DoorGrant>,
+                       to: &HashMap<(Uuid, Uuid), DoorGrant>| {
+            let mut grants: Vec<DoorGrant> = from
+                .iter()
+                .filter(|(key, _)| !to.contains_key(key))
+                .map(|(_, grant)| grant.clone())
+                .collect();
+            grants.sort_by(|a, b| {
+                a.person
+                    .name
+                    .cmp(&b.person.name)
+                    .then(a.person.id.cmp(&b.person.id))
+                    .then(a.door.name.cmp(&b.door.name))
+                    .then(a.door.id.cmp(&b.door.id))
+            });
+            grants
+        };
+
+        AccessDiff {
+            from: self.taken_at,
+            to: later.taken_at,
+            added: changes(&after, &before),
+            removed: changes(&before, &after),
+        }
+    }
+}
+
+/// Person to door grants gained and lost between two snapshots
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessDiff {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub added: Vec<DoorGrant>,
+    pub removed: Vec<DoorGrant>,
+}
+
+impl AccessDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+fn display_name(relation: &Relation) -> &str {
+    relation.name.as_deref().unwrap_or_default()
+}
+
+impl fmt::Display for AccessDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Access changes from {} to {}",
+            self.from.to_rfc3339(),
+            self.to.to_rfc3339()
+        )?;
+
+        for (sign, grants) in [("+", &self.added), ("-", &self.removed)] {
+            for grant in grants {
+                writeln!(
+                    f,
+                    "{sign} {} ({}) -> {} ({})",
+                    display_name(&grant.person),
+                    grant.person.id,
+                    display_name(&grant.door),
+                    grant.door.id
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::asset::Asset;
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn snapshot(door: u128, month: u32) -> AccessSnapshot {
+        let tenant = Tenant::new(vec![
+            serde_json::from_value::<Agent>(json!({
+                "type": "person",
+                "id": id(1),
+                "name": "Jane",
+                "memberOf": [{"id": id(3)}],
+            }))
+            .unwrap()
+            .into(),
+            serde_json::from_value::<Agent>(json!({"type": "person", "id": id(2), "name": "John"}))
+                .unwrap()
+                .into(),
+            serde_json::from_value::<Agent>(json!({
+                "type": "access_group",
+                "id": id(3),
+                "name": "Staff",
+                "hasMember": [{"id": id(2)}],
+                "includesDoor": [{"id": id(door)}],
+            }))
+            .unwrap()
+            .into(),
+            serde_json::from_value::<Asset>(json!({"type": "door", "id": id(10), "name": "Front"}))
+                .unwrap()
+                .into(),
+            serde_json::from_value::<Asset>(json!({"type": "door", "id": id(11), "name": "Back"}))
+                .unwrap()
+                .into(),
+        ]);
+
+        AccessSnapshot::new(
+            &tenant,
+            Utc.with_ymd_and_hms(2024, month, 1, 0, 0, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn members_are_merged_from_both_sides() {
+        let snapshot = snapshot(10, 1);
+
+        let mut members: Vec<Uuid> = snapshot.groups[0]
+            .members
+            .iter()
+            .map(|member| member.id)
+            .collect();
+        members.sort();
+        assert_eq!(members, [id(1), id(2)]);
+        assert_eq!(snapshot.grants.len(), 2);
+    }
+
+    #[test]
+    fn diff_reports_added_and_removed_grants() {
+        let path =
+            std::env::temp_dir().join(format!("access-snapshot-{}.json", std::process::id()));
+        snapshot(10, 1).save(&path).unwrap();
+        let before = AccessSnapshot::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let diff = before.diff(&snapshot(11, 4));
+
+        let grants = |grants: &[DoorGrant]| -> Vec<(Uuid, Uuid)> {
+            grants
+                .iter()
+                .map(|grant| (grant.person.id, grant.door.id))
+                .collect()
+        };
+        assert_eq!(grants(&diff.added), [(id(1), id(11)), (id(2), id(11))]);
+        assert_eq!(grants(&diff.removed), [(id(1), id(10)), (id(2), id(10))]);
+        assert!(before.diff(&before).is_empty());
+    }
+}
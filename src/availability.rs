@@ -0,0 +1,270 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{
+        event::{Booking, Event, EventType},
+        space::Space,
+        Relation,
+    },
+    spatial::SpatialTree,
+};
+
+/// Bookable space wanted for `start <= t < end`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityQuery {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Minimum number of people the space must hold
+    pub min_capacity: u32,
+    /// Building or level to search in, every space if not set
+    pub scope: Option<Uuid>,
+}
+
+/// Free bookable space
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableSpace {
+    pub space: Relation,
+    pub capacity: Option<f64>,
+    /// Capacity left over after seating `min_capacity`
+    pub surplus: Option<f64>,
+}
+
+/// Bookable spaces and the bookings made for them
+#[derive(Debug, Clone, Default)]
+pub struct Availability {
+    tree: SpatialTree,
+    bookings: Vec<Booking>,
+}
+
+/// Whether `a_start <= t < a_end` and `b_start <= t < b_end` share any `t`
+pub fn overlaps(
+    a_start: DateTime<Utc>,
+    a_end: DateTime<Utc>,
+    b_start: DateTime<Utc>,
+    b_end: DateTime<Utc>,
+) -> bool {
+    a_start < b_end && b_start < a_end
+}
+
+/// Number of people the space holds, seating capacity
+/// first, then maximum occupancy
+pub fn capacity(space: &Space) -> Option<f64> {
+    space
+        .capacity()
+        .and_then(|capacity| capacity.seating_capacity.or(capacity.max_occupancy))
+}
+
+impl Availability {
+    /// Load spaces and bookings
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let tree = SpatialTree::load(handler, client, token).await?;
+        let bookings = handler
+            .events(client, token, EventType::Booking)
+            .await?
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Booking(booking) => Some(booking),
+                _ => None,
+            })
+            .collect();
+
+        Ok(Self { tree, bookings })
+    }
+
+    pub fn new(spaces: Vec<Space>, bookings: Vec<Booking>) -> Self {
+        Self {
+            tree: SpatialTree::new(spaces),
+            bookings,
+        }
+    }
+
+    pub fn bookings(&self) -> &[Booking] {
+        &self.bookings
+    }
+
+    /// Bookings of the space overlapping `start <= t < end`
+    pub fn conflicts(
+        &self,
+        space: &Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Vec<&Booking> {
+        self.bookings
+            .iter()
+            .filter(|booking| booking.room.as_ref().map(|room| room.id) == Some(*space))
+            .filter(|booking| overlaps(booking.start, booking.effective_end(), start, end))
+            .collect()
+    }
+
+    pub fn is_free(&self, space: &Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        self.conflicts(space, start, end).is_empty()
+    }
+
+    /// Free bookable spaces holding at least `min_capacity` people,
+    /// smallest fitting space first
+    ///
+    /// Spaces without a known capacity are only returned when
+    /// `min_capacity` is zero, after every space with one.
+    pub fn search(&self, query: &AvailabilityQuery) -> Vec<AvailableSpace> {
+        if query.start >= query.end {
+            return Vec::new();
+        }
+
+        let candidates: Vec<&Space> = match &query.scope {
+            Some(scope) => self.tree.descendants(scope),
+            None => self.tree.spaces().collect(),
+        };
+
+        let min_capacity = f64::from(query.min_capacity);
+
+        let mut available: Vec<AvailableSpace> = candidates
+            .into_iter()
+            .filter(|space| space.is_bookable())
+            .filter(|space| match capacity(space) {
+                Some(capacity) => capacity >= min_capacity,
+                None => query.min_capacity == 0,
+            })
+            .filter(|space| self.is_free(&space.id(), query.start, query.end))
+            .map(|space| {
+                let capacity = capacity(space);
+
+                AvailableSpace {
+                    space: Relation::to(space),
+                    capacity,
+                    surplus: capacity.map(|capacity| capacity - min_capacity),
+                }
+            })
+            .collect();
+
+        available.sort_by(|a, b| {
+            let surplus = |space: &AvailableSpace| space.surplus.unwrap_or(f64::INFINITY);
+
+            surplus(a)
+                .total_cmp(&surplus(b))
+                .then(a.space.name.cmp(&b.space.name))
+                .then(a.space.id.cmp(&b.space.id))
+        });
+
+        available
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    fn room(room: u128, name: &str, seats: Option<f64>) -> Space {
+        serde_json::from_value(json!({
+            "type": "room",
+            "id": id(room),
+            "name": name,
+            "bookable": true,
+            "capacity": seats.map(|seats| json!({
+                "id": id(room + 100),
+                "type": "capacity",
+                "name": "Capacity",
+                "seatingCapacity": seats,
+            })),
+        }))
+        .unwrap()
+    }
+
+    fn availability() -> Availability {
+        let spaces = vec![
+            serde_json::from_value(json!({
+                "type": "building",
+                "id": id(1),
+                "name": "HQ",
+                "hasPart": [{"id": id(2)}, {"id": id(3)}, {"id": id(4)}],
+            }))
+            .unwrap(),
+            room(2, "Large", Some(12.)),
+            room(3, "Small", Some(4.)),
+            room(4, "Medium", Some(6.)),
+            room(5, "Annex", None),
+        ];
+
+        let booking = serde_json::from_value(json!({
+            "id": id(50),
+            "name": "Planning",
+            "start": at(9),
+            "end": at(10),
+            "room": {"id": id(4)},
+        }))
+        .unwrap();
+
+        Availability::new(spaces, vec![booking])
+    }
+
+    fn search(query: AvailabilityQuery) -> Vec<Uuid> {
+        availability()
+            .search(&query)
+            .iter()
+            .map(|available| available.space.id)
+            .collect()
+    }
+
+    #[test]
+    fn smallest_free_space_comes_first() {
+        let query = AvailabilityQuery {
+            start: at(9),
+            end: at(11),
+            min_capacity: 3,
+            scope: Some(id(1)),
+        };
+        assert_eq!(search(query), [id(3), id(2)]);
+
+        let query = AvailabilityQuery {
+            start: at(10),
+            end: at(11),
+            min_capacity: 5,
+            scope: None,
+        };
+        assert_eq!(search(query), [id(4), id(2)]);
+    }
+
+    #[test]
+    fn spaces_without_capacity_only_match_zero() {
+        let query = AvailabilityQuery {
+            start: at(10),
+            end: at(11),
+            min_capacity: 0,
+            scope: None,
+        };
+        assert_eq!(search(query), [id(3), id(4), id(2), id(5)]);
+
+        let query = AvailabilityQuery {
+            start: at(11),
+            end: at(10),
+            min_capacity: 0,
+            scope: None,
+        };
+        assert!(search(query).is_empty());
+    }
+
+    #[test]
+    fn touching_periods_do_not_overlap() {
+        assert!(overlaps(at(9), at(10), at(9), at(11)));
+        assert!(!overlaps(at(9), at(10), at(10), at(11)));
+        assert!(availability().is_free(&id(4), at(10), at(11)));
+        assert!(!availability().is_free(&id(4), at(8), at(10)));
+    }
+}
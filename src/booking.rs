@@ -0,0 +1,372 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    availability,
+    error::Error,
+    handler::Handler,
+    models::{
+        event::{Booking, Event, EventType, NewEvent},
+        Id, Identifier,
+    },
+};
+
+/// How long before its start a booking can be checked in to
+pub const EARLY_CHECK_IN_MINUTES: i64 = 15;
+
+/// Booking of a space for `start <= t < end`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoomBooking {
+    pub name: String,
+    pub room: Uuid,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub lease: Uuid,
+    pub booked_by: Option<Uuid>,
+    pub identifiers: Option<Vec<Identifier>>,
+}
+
+impl From<RoomBooking> for NewEvent {
+    fn from(value: RoomBooking) -> Self {
+        NewEvent::Booking {
+            name: value.name,
+            start: value.start.to_rfc3339(),
+            end: value.end.to_rfc3339(),
+            identifiers: value.identifiers,
+            booked_by: value.booked_by.map(|id| Id { id }),
+            lease: Id { id: value.lease },
+            room: Some(Id { id: value.room }),
+        }
+    }
+}
+
+/// Get every booking
+pub async fn bookings(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+) -> Result<Vec<Booking>, Error> {
+    let bookings = handler
+        .events(client, token, EventType::Booking)
+        .await?
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Booking(booking) => Some(booking),
+            _ => None,
+        })
+        .collect();
+
+    Ok(bookings)
+}
+
+/// Bookings of the room overlapping `start <= t < end`
+pub fn overlapping<'a>(
+    bookings: impl IntoIterator<Item = &'a Booking>,
+    room: &Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<&'a Booking> {
+    bookings
+        .into_iter()
+        .filter(|booking| booking.room.as_ref().map(|room| room.id) == Some(*room))
+        .filter(|booking| {
+            availability::overlaps(booking.start, booking.effective_end(), start, end)
+        })
+        .collect()
+}
+
+/// Get bookings of the room overlapping `start <= t < end`
+pub async fn conflicts(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    room: &Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Booking>, Error> {
+    let bookings = bookings(handler, client, token).await?;
+
+    Ok(overlapping(&bookings, room, start, end)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// Bookings that win over booking `id` when they overlap
+///
+/// The booking with the lowest id is kept, so every request
+/// seeing the same overlapping bookings agrees on the winner.
+fn winning(id: Uuid, clashing: impl IntoIterator<Item = Uuid>) -> Vec<Uuid> {
+    clashing.into_iter().filter(|other| *other < id).collect()
+}
+
+/// Book a room if it is bookable and free
+///
+/// Bookings are checked again once created. If bookings made
+/// concurrently overlap, the one with the lowest id is kept and
+/// the others are deleted and report the conflict, so exactly
+/// one of two overlapping requests succeeds.
+pub async fn book_room(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    booking: RoomBooking,
+) -> Result<Uuid, Error> {
+    if booking.start >= booking.end {
+        return Err(Error::InvalidBooking(
+            "booking must start before it ends".into(),
+        ));
+    }
+
+    let space = handler.space(client, token, &booking.room).await?;
+
+    if !space.is_bookable() {
+        return Err(Error::NotBookable(booking.room));
+    }
+
+    let (room, start, end) = (booking.room, booking.start, booking.end);
+
+    let clashing = conflicts(handler, client, token, &room, start, end).await?;
+
+    if !clashing.is_empty() {
+        return Err(Error::BookingConflict(
+            clashing.iter().map(|booking| booking.id).collect(),
+        ));
+    }
+
+    let id = handler.create_event(client, token, booking.into()).await?;
+
+    let clashing = winning(
+        id,
+        conflicts(handler, client, token, &room, start, end)
+            .await?
+            .iter()
+            .map(|booking| booking.id),
+    );
+
+    if !clashing.is_empty() {
+        tracing::info!("Rolling back booking {id} of {room}");
+        handler.delete_event(client, token, &id).await?;
+
+        return Err(Error::BookingConflict(clashing));
+    }
+
+    Ok(id)
+}
+
+/// Get a booking by id
+async fn booking(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    id: &Uuid,
+) -> Result<Booking, Error> {
+    match handler.event(client, token, id).await? {
+        Event::Booking(booking) => Ok(booking),
+        event => Err(Error::InvalidBooking(format!(
+            "event {id} is a {}, not a booking",
+            event.event_type().to_string()
+        ))),
+    }
+}
+
+/// Whether a booking can be checked in to at `at`
+fn check_in_allowed(booking: &Booking, at: DateTime<Utc>) -> Result<(), Error> {
+    if booking.released_at().is_some() {
+        return Err(Error::InvalidBooking(format!(
+            "booking {} was released",
+            booking.id
+        )));
+    }
+
+    if at < booking.start - Duration::minutes(EARLY_CHECK_IN_MINUTES) {
+        return Err(Error::InvalidBooking(format!(
+            "booking {} starts at {}",
+            booking.id, booking.start
+        )));
+    }
+
+    if at >= booking.end {
+        return Err(Error::InvalidBooking(format!(
+            "booking {} ended at {}",
+            booking.id, booking.end
+        )));
+    }
+
+    Ok(())
+}
+
+/// Record that the room of a booking was taken at `at`
+///
+/// Checking in twice keeps the first check-in. Released and
+/// ended bookings can not be checked in, nor bookings starting
+/// more than [`EARLY_CHECK_IN_MINUTES`] after `at`.
+pub async fn check_in(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    id: &Uuid,
+    at: DateTime<Utc>,
+) -> Result<Booking, Error> {
+    let mut booking = booking(handler, client, token, id).await?;
+
+    if booking.checked_in_at().is_some() {
+        return Ok(booking);
+    }
+
+    check_in_allowed(&booking, at)?;
+
+    booking.mark_checked_in(at);
+    handler
+        .replace_event(client, token, Event::Booking(booking.clone()))
+        .await?;
+
+    Ok(booking)
+}
+
+/// Releases bookings nobody checked in to within `grace` of their start
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleasePolicy {
+    pub grace_minutes: u32,
+}
+
+impl ReleasePolicy {
+    pub fn new(grace_minutes: u32) -> Self {
+        Self { grace_minutes }
+    }
+
+    /// Whether nobody checked in to the booking within the grace
+    /// period, bookings that ended without a check-in included
+    pub fn is_no_show(&self, booking: &Booking, now: DateTime<Utc>) -> bool {
+        let deadline = booking.start + Duration::minutes(self.grace_minutes.into());
+
+        booking.checked_in_at().is_none() && booking.released_at().is_none() && deadline <= now
+    }
+
+    /// Whether the booking is a no-show still running at `now`
+    fn should_release(&self, booking: &Booking, now: DateTime<Utc>) -> bool {
+        self.is_no_show(booking, now) && now < booking.end
+    }
+
+    /// Release the running no-show bookings at `now`, returning their ids
+    ///
+    /// Bookings that already ended are left as they are.
+    pub async fn release(
+        &self,
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Uuid>, Error> {
+        let mut released = Vec::new();
+
+        for mut booking in bookings(handler, client, token).await? {
+            if !self.should_release(&booking, now) {
+                continue;
+            }
+
+            tracing::info!("Releasing booking {} without check-in", booking.id);
+            booking.mark_released(now);
+            released.push(booking.id);
+
+            handler
+                .replace_event(client, token, Event::Booking(booking))
+                .await?;
+        }
+
+        Ok(released)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, hour, minute, 0).unwrap()
+    }
+
+    fn booking() -> Booking {
+        Booking {
+            id: Uuid::from_u128(1),
+            name: "Planning".into(),
+            start: at(9, 0),
+            end: at(10, 0),
+            identifiers: None,
+            booked_by: None,
+            lease: None,
+            room: None,
+        }
+    }
+
+    #[test]
+    fn lowest_id_wins_concurrent_bookings() {
+        let (first, second) = (Uuid::from_u128(1), Uuid::from_u128(2));
+
+        assert_eq!(winning(second, [first, second]), [first]);
+        assert!(winning(first, [first, second]).is_empty());
+    }
+
+    #[test]
+    fn check_in_is_kept_in_the_identifiers() {
+        let mut booking = booking();
+        booking.mark_checked_in(at(9, 5));
+        booking.mark_checked_in(at(9, 10));
+
+        let json = serde_json::to_value(&booking).unwrap();
+        let booking: Booking = serde_json::from_value(json).unwrap();
+
+        assert_eq!(booking.checked_in_at(), Some(at(9, 5)));
+        assert_eq!(booking.released_at(), None);
+        assert_eq!(
+            booking.identifiers.map(|identifiers| identifiers.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn check_in_is_limited_to_the_booking() {
+        let booking = booking();
+
+        assert!(check_in_allowed(&booking, at(8, 45)).is_ok());
+        assert!(check_in_allowed(&booking, at(9, 59)).is_ok());
+        assert!(matches!(
+            check_in_allowed(&booking, at(8, 44)),
+            Err(Error::InvalidBooking(_))
+        ));
+        assert!(matches!(
+            check_in_allowed(&booking, at(10, 0)),
+            Err(Error::InvalidBooking(_))
+        ));
+
+        let mut released = booking;
+        released.mark_released(at(9, 15));
+        assert!(matches!(
+            check_in_allowed(&released, at(9, 30)),
+            Err(Error::InvalidBooking(_))
+        ));
+    }
+
+    #[test]
+    fn ended_bookings_without_check_in_are_no_shows() {
+        let policy = ReleasePolicy::new(15);
+        let booking = booking();
+
+        assert!(!policy.is_no_show(&booking, at(9, 14)));
+        assert!(policy.is_no_show(&booking, at(9, 15)));
+        assert!(policy.is_no_show(&booking, at(11, 0)));
+
+        assert!(policy.should_release(&booking, at(9, 15)));
+        assert!(!policy.should_release(&booking, at(10, 0)));
+
+        let mut checked_in = booking;
+        checked_in.mark_checked_in(at(9, 5));
+        assert!(!policy.is_no_show(&checked_in, at(11, 0)));
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{Entity, Relation, Resource},
+    tenant::Tenant,
+};
+
+/// Fields through which an entity contains its children
+const CONTAINS: [&str; 3] = ["has_part", "is_location_of", "has_point"];
+
+/// Fields through which an entity points at its container
+const CONTAINED_BY: [&str; 3] = ["is_part_of", "located_in", "is_point_of"];
+
+/// What to do with entities depending on a deleted entity
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CascadePolicy {
+    /// Delete contained spaces, assets and points, detach everything else
+    Delete,
+    /// Keep contained spaces, assets and points, removing their relations
+    /// to the deleted entity
+    Detach,
+    /// Do not delete while anything depends on the entity
+    Refuse,
+}
+
+/// Relation from `source` to an entity that is deleted
+#[derive(Serialize, Debug, Clone)]
+pub struct Reference {
+    pub source: Relation,
+    pub field: &'static str,
+    pub target: Relation,
+}
+
+/// Everything affected by deleting an entity
+#[derive(Serialize, Debug, Clone)]
+pub struct CascadePlan {
+    pub policy: CascadePolicy,
+    pub root: Relation,
+    /// Spaces, assets and points contained only by the root, directly or
+    /// through other contained entities
+    pub children: Vec<Relation>,
+    /// Entities to delete, children before their containers
+    pub delete: Vec<Relation>,
+    /// Relations to remove from entities that are kept
+    pub detach: Vec<Reference>,
+}
+
+impl CascadePlan {
+    /// Compute the dependency graph of `id` in `tenant`
+    pub fn new(tenant: &Tenant, id: &Uuid, policy: CascadePolicy) -> Result<Self, Error> {
+        let root = tenant.get(id).ok_or(Error::NotInTenant(*id))?;
+
+        let children = Self::children(tenant, root);
+
+        let deleted: HashSet<Uuid> = match policy {
+            CascadePolicy::Delete => children
+                .iter()
+                .map(|child| child.id)
+                .chain([root.id()])
+                .collect(),
+            CascadePolicy::Detach | CascadePolicy::Refuse => HashSet::from([root.id()]),
+        };
+
+        let mut detach = Vec::new();
+
+        for entity in tenant.entities() {
+            if deleted.contains(&entity.id()) {
+                continue;
+            }
+
+            for (field, relations) in entity.relations() {
+                for relation in relations.iter().filter(|r| deleted.contains(&r.id)) {
+                    detach.push(Reference {
+                        source: Relation::to(entity),
+                        field,
+                        target: tenant
+                            .get(&relation.id)
+                            .map(Relation::to)
+                            .unwrap_or_else(|| relation.clone()),
+                    });
+                }
+            }
+        }
+
+        let mut delete: Vec<Relation> = match policy {
+            CascadePolicy::Delete => children.iter().rev().cloned().collect(),
+            CascadePolicy::Detach | CascadePolicy::Refuse => Vec::new(),
+        };
+        delete.push(Relation::to(root));
+
+        Ok(Self {
+            policy,
+            root: Relation::to(root),
+            children,
+            delete,
+            detach,
+        })
+    }
+
+    /// Contained entities in the order they were reached from `root`
+    ///
+    /// An entity with several containers is only included
+    /// once all of them are included.
+    fn children(tenant: &Tenant, root: &Entity) -> Vec<Relation> {
+        let mut containers: HashMap<Uuid, HashSet<Uuid>> = HashMap::new();
+
+        for entity in tenant.entities() {
+            for (field, relations) in entity.relations() {
+                for relation in relations.iter().filter(|r| tenant.contains(&r.id)) {
+                    if CONTAINS.contains(&field) {
+                        containers
+                            .entry(relation.id)
+                            .or_default()
+                            .insert(entity.id());
+                    } else if CONTAINED_BY.contains(&field) {
+                        containers
+                            .entry(entity.id())
+                            .or_default()
+                            .insert(relation.id);
+                    }
+                }
+            }
+        }
+
+        let mut included = HashSet::from([root.id()]);
+        let mut children = Vec::new();
+
+        loop {
+            let mut next: Vec<&Entity> = tenant
+                .entities()
+                .iter()
+                .filter(|entity| !included.contains(&entity.id()))
+                .filter(|entity| {
+                    containers.get(&entity.id()).is_some_and(|containers| {
+                        containers
+                            .iter()
+                            .all(|container| included.contains(container))
+                    })
+                })
+                .collect();
+
+            if next.is_empty() {
+                break;
+            }
+
+            next.sort_by_key(|entity| entity.id());
+
+            for entity in next {
+                included.insert(entity.id());
+                children.push(Relation::to(entity));
+            }
+        }
+
+        children
+    }
+
+    /// Entities that prevent a delete under `CascadePolicy::Refuse`
+    ///
+    /// Containers listing the root in `has_part`, `is_location_of`
+    /// or `has_point` do not count as dependents.
+    pub fn dependents(&self) -> Vec<Uuid> {
+        let mut dependents: Vec<Uuid> = self.children.iter().map(|child| child.id).collect();
+
+        for reference in &self.detach {
+            if !CONTAINS.contains(&reference.field) && !dependents.contains(&reference.source.id) {
+                dependents.push(reference.source.id);
+            }
+        }
+
+        dependents
+    }
+
+    /// Detach references, then delete entities in plan order
+    ///
+    /// `tenant` must be the snapshot the plan was made from.
+    pub async fn execute(
+        &self,
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        tenant: &Tenant,
+    ) -> Result<(), Error> {
+        if self.policy == CascadePolicy::Refuse {
+            let dependents = self.dependents();
+
+            if !dependents.is_empty() {
+                return Err(Error::HasDependents(dependents));
+            }
+        }
+
+        let deleted: HashSet<Uuid> = self.delete.iter().map(|relation| relation.id).collect();
+        let mut sources: Vec<Uuid> = Vec::new();
+
+        for reference in &self.detach {
+            if !sources.contains(&reference.source.id) {
+                sources.push(reference.source.id);
+            }
+        }
+
+        for id in sources {
+            let Some(mut entity) = tenant.get(&id).cloned() else {
+                continue;
+            };
+
+            let mut removed = 0;
+
+            for (_, mut field) in entity.relation_fields() {
+                removed += field.retain(|relation| !deleted.contains(&relation.id));
+            }
+
+            if removed > 0 {
+                handler.replace(client, token, entity).await?;
+            }
+        }
+
+        for relation in &self.delete {
+            let Some(entity) = tenant.get(&relation.id) else {
+                continue;
+            };
+
+            tracing::info!("Deleting {} {}", entity.rtype(), entity.id());
+
+            handler.delete(client, token, entity).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CascadePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Delete:")?;
+
+        for relation in &self.delete {
+            writeln!(
+                f,
+                "  {} {} ({})",
+                relation.rtype.as_deref().unwrap_or_default(),
+                relation.name.as_deref().unwrap_or_default(),
+                relation.id
+            )?;
+        }
+
+        writeln!(f, "Detach:")?;
+
+        for reference in &self.detach {
+            writeln!(
+                f,
+                "  {} {}.{} -> {}",
+                reference.source.rtype.as_deref().unwrap_or_default(),
+                reference.source.name.as_deref().unwrap_or_default(),
+                reference.field,
+                reference.target.name.as_deref().unwrap_or_default(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::{asset::Asset, collection::Collection, event::Event, space::Space};
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn space(space_type: &str, space: u128, relations: Value) -> Entity {
+        let mut value =
+            json!({"type": space_type, "id": id(space), "name": format!("Space {space}")});
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(relations.as_object().unwrap().clone());
+
+        serde_json::from_value::<Space>(value).unwrap().into()
+    }
+
+    fn tenant() -> Tenant {
+        Tenant::new(vec![
+            space("building", 1, json!({"hasPart": [{"id": id(2)}]})),
+            space("level", 2, json!({"isPartOf": [{"id": id(1)}]})),
+            space("room", 3, json!({"isPartOf": [{"id": id(2)}]})),
+            space(
+                "access_control_zone",
+                4,
+                json!({"isPartOf": [{"id": id(2)}, {"id": id(10)}]}),
+            ),
+            space("building", 10, json!({})),
+            serde_json::from_value::<Asset>(json!({
+                "type": "door",
+                "id": id(5),
+                "name": "Door",
+                "locatedIn": [{"id": id(3)}],
+            }))
+            .unwrap()
+            .into(),
+            serde_json::from_value::<Event>(json!({
+                "type": "lease",
+                "id": id(6),
+                "name": "Lease",
+                "start": "2024-01-01T00:00:00Z",
+                "leaseOf": [{"id": id(3)}],
+            }))
+            .unwrap()
+            .into(),
+            serde_json::from_value::<Collection>(json!({
+                "type": "apartment",
+                "id": id(7),
+                "name": "Apartment",
+                "includes": [{"id": id(3)}, {"id": id(10)}],
+            }))
+            .unwrap()
+            .into(),
+        ])
+    }
+
+    fn ids(relations: &[Relation]) -> Vec<Uuid> {
+        relations.iter().map(|relation| relation.id).collect()
+    }
+
+    fn references(plan: &CascadePlan) -> Vec<(Uuid, &'static str, Uuid)> {
+        let mut references: Vec<_> = plan
+            .detach
+            .iter()
+            .map(|reference| (reference.source.id, reference.field, reference.target.id))
+            .collect();
+        references.sort();
+        references
+    }
+
+    #[test]
+    fn delete_removes_children_before_their_containers() {
+        let plan = CascadePlan::new(&tenant(), &id(1), CascadePolicy::Delete).unwrap();
+
+        assert_eq!(ids(&plan.children), [id(2), id(3), id(5)]);
+        assert_eq!(ids(&plan.delete), [id(5), id(3), id(2), id(1)]);
+        assert_eq!(
+            references(&plan),
+            [
+                (id(4), "is_part_of", id(2)),
+                (id(6), "lease_of", id(3)),
+                (id(7), "includes", id(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn detach_keeps_the_children() {
+        let plan = CascadePlan::new(&tenant(), &id(2), CascadePolicy::Detach).unwrap();
+
+        assert_eq!(ids(&plan.delete), [id(2)]);
+        assert_eq!(
+            references(&plan),
+            [
+                (id(1), "has_part", id(2)),
+                (id(3), "is_part_of", id(2)),
+                (id(4), "is_part_of", id(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn containers_do_not_count_as_dependents() {
+        let tenant = tenant();
+
+        let plan = CascadePlan::new(&tenant, &id(2), CascadePolicy::Refuse).unwrap();
+        assert_eq!(plan.dependents(), [id(3), id(5), id(4)]);
+
+        let plan = CascadePlan::new(&tenant, &id(5), CascadePolicy::Refuse).unwrap();
+        assert!(plan.dependents().is_empty());
+
+        assert!(matches!(
+            CascadePlan::new(&tenant, &id(99), CascadePolicy::Refuse),
+            Err(Error::NotInTenant(_))
+        ));
+    }
+}
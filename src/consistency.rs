@@ -0,0 +1,406 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{space::Space, Entity, Relation, RelationField, Resource},
+    tenant::Tenant,
+};
+
+/// Relation field and the field on its target pointing back
+struct Pair {
+    field: &'static str,
+    inverse: &'static str,
+    /// Targets the pair applies to
+    target: fn(&Entity) -> bool,
+}
+
+const PAIRS: [Pair; 6] = [
+    Pair {
+        field: "has_part",
+        inverse: "is_part_of",
+        target: is_space,
+    },
+    Pair {
+        field: "has_member",
+        inverse: "member_of",
+        target: is_agent,
+    },
+    Pair {
+        field: "includes_zone",
+        inverse: "included_in",
+        target: is_zone,
+    },
+    Pair {
+        field: "includes",
+        inverse: "included_in",
+        target: is_space,
+    },
+    Pair {
+        field: "is_location_of",
+        inverse: "located_in",
+        target: is_asset,
+    },
+    Pair {
+        field: "has_point",
+        inverse: "is_point_of",
+        target: is_point,
+    },
+];
+
+fn is_space(entity: &Entity) -> bool {
+    matches!(entity, Entity::Space(_))
+}
+
+fn is_agent(entity: &Entity) -> bool {
+    matches!(entity, Entity::Agent(_))
+}
+
+fn is_asset(entity: &Entity) -> bool {
+    matches!(entity, Entity::Asset(_))
+}
+
+fn is_point(entity: &Entity) -> bool {
+    matches!(entity, Entity::Point(_))
+}
+
+fn is_zone(entity: &Entity) -> bool {
+    matches!(entity, Entity::Space(Space::AccessControlZone(_)))
+}
+
+fn has_field(entity: &Entity, name: &str) -> bool {
+    entity.relations().iter().any(|(field, _)| *field == name)
+}
+
+fn field_contains(entity: &Entity, name: &str, id: &Uuid) -> bool {
+    entity
+        .relations()
+        .iter()
+        .filter(|(field, _)| *field == name)
+        .any(|(_, relations)| relations.iter().any(|relation| relation.id == *id))
+}
+
+/// Field on `target` mirroring `field` on `source`, in either
+/// direction of a pair. Returns the static names of both fields.
+fn pair_for(source: &Entity, field: &str, target: &Entity) -> Option<(&'static str, &'static str)> {
+    if !has_field(source, field) {
+        return None;
+    }
+
+    PAIRS.iter().find_map(|pair| {
+        if pair.field == field && (pair.target)(target) && has_field(target, pair.inverse) {
+            Some((pair.field, pair.inverse))
+        } else if pair.inverse == field && (pair.target)(source) && has_field(target, pair.field) {
+            Some((pair.inverse, pair.field))
+        } else {
+            None
+        }
+    })
+}
+
+/// Relation whose target does not point back at its source
+#[derive(Serialize, Debug, Clone)]
+pub struct OneSided {
+    pub source: Relation,
+    pub field: &'static str,
+    pub target: Relation,
+    /// Field on `target` that should contain `source`
+    pub missing: &'static str,
+}
+
+/// Every one-sided relation pair in a tenant
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ConsistencyReport {
+    pub one_sided: Vec<OneSided>,
+}
+
+impl ConsistencyReport {
+    /// Check both sides of every paired relation in `tenant`,
+    /// relations to missing entities are ignored
+    pub fn check(tenant: &Tenant) -> Self {
+        let mut one_sided = Vec::new();
+
+        for source in tenant.entities() {
+            for (field, relations) in source.relations() {
+                for relation in relations {
+                    let Some(target) = tenant.get(&relation.id) else {
+                        continue;
+                    };
+
+                    let Some((field, missing)) = pair_for(source, field, target) else {
+                        continue;
+                    };
+
+                    if !field_contains(target, missing, &source.id()) {
+                        one_sided.push(OneSided {
+                            source: Relation::to(source),
+                            field,
+                            target: Relation::to(target),
+                            missing,
+                        });
+                    }
+                }
+            }
+        }
+
+        Self { one_sided }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.one_sided.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.one_sided.len()
+    }
+
+    /// Add the missing side of every pair through the replace
+    /// endpoints, returns the ids of the updated entities
+    ///
+    /// `tenant` must be the snapshot the report was made from.
+    pub async fn repair(
+        &self,
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        tenant: &Tenant,
+    ) -> Result<Vec<Uuid>, Error> {
+        let mut targets: BTreeMap<Uuid, Vec<&OneSided>> = BTreeMap::new();
+
+        for one_sided in &self.one_sided {
+            targets
+                .entry(one_sided.target.id)
+                .or_default()
+                .push(one_sided);
+        }
+
+        let mut repaired = Vec::new();
+
+        for (id, missing) in targets {
+            let Some(mut target) = tenant.get(&id).cloned() else {
+                continue;
+            };
+
+            let mut changed = false;
+
+            for one_sided in missing {
+                let Some(source) = tenant.get(&one_sided.source.id) else {
+                    continue;
+                };
+
+                if let Some(mut field) = target.relation_field(one_sided.missing) {
+                    changed |= field.insert(Relation::to(source));
+                }
+            }
+
+            if changed {
+                handler.replace(client, token, target).await?;
+                repaired.push(id);
+            }
+        }
+
+        Ok(repaired)
+    }
+}
+
+impl fmt::Display for ConsistencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for one_sided in &self.one_sided {
+            writeln!(
+                f,
+                "{} {}: {} {} has no {} back",
+                one_sided.source.name.as_deref().unwrap_or_default(),
+                one_sided.field,
+                one_sided.target.rtype.as_deref().unwrap_or_default(),
+                one_sided.target.name.as_deref().unwrap_or_default(),
+                one_sided.missing,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Add `target` to `field` of `source` and `source` to the
+/// mirrored field of `target`, replacing both entities
+pub async fn link(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    source: &Entity,
+    field: &str,
+    target: &Entity,
+) -> Result<(), Error> {
+    update_pair(
+        handler,
+        client,
+        token,
+        source,
+        field,
+        target,
+        |field, other| field.insert(Relation::to(other)),
+    )
+    .await
+}
+
+/// Remove `target` from `field` of `source` and `source` from
+/// the mirrored field of `target`, replacing both entities
+pub async fn unlink(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    source: &Entity,
+    field: &str,
+    target: &Entity,
+) -> Result<(), Error> {
+    update_pair(
+        handler,
+        client,
+        token,
+        source,
+        field,
+        target,
+        |field, other| field.retain(|relation| relation.id != other.id()) > 0,
+    )
+    .await
+}
+
+/// Apply `update` to both sides of a pair and replace the changed
+/// entities. If replacing `target` fails, `source` is restored.
+async fn update_pair(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    source: &Entity,
+    field: &str,
+    target: &Entity,
+    update: impl Fn(&mut RelationField<'_>, &Entity) -> bool,
+) -> Result<(), Error> {
+    if source.id() == target.id() {
+        return Err(Error::InvalidRelation(format!(
+            "{} can not be related to itself",
+            source.id()
+        )));
+    }
+
+    let (field, inverse) = pair_for(source, field, target).ok_or_else(|| {
+        Error::InvalidRelation(format!(
+            "{field} of {} has no inverse on {}",
+            source.rtype(),
+            target.rtype()
+        ))
+    })?;
+
+    let mut new_source = source.clone();
+    let mut new_target = target.clone();
+
+    let source_changed = new_source
+        .relation_field(field)
+        .is_some_and(|mut field| update(&mut field, target));
+    let target_changed = new_target
+        .relation_field(inverse)
+        .is_some_and(|mut field| update(&mut field, source));
+
+    if source_changed {
+        handler.replace(client, token, new_source).await?;
+    }
+
+    if target_changed {
+        if let Err(e) = handler.replace(client, token, new_target).await {
+            if source_changed {
+                if let Err(restore) = handler.replace(client, token, source.clone()).await {
+                    tracing::error!("Failed to restore {}: {}", source.id(), restore);
+                }
+            }
+
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::agent::Agent;
+
+    fn space(value: Value) -> Entity {
+        serde_json::from_value::<Space>(value).unwrap().into()
+    }
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    #[test]
+    fn one_sided_pairs_are_reported() {
+        let group = serde_json::from_value::<Agent>(json!({
+            "type": "access_group",
+            "id": id(3),
+            "name": "Staff",
+            "includesDoor": [{"id": id(4)}],
+            "includesZone": [{"id": id(5)}],
+        }))
+        .unwrap();
+
+        let tenant = Tenant::new(vec![
+            space(
+                json!({"type": "building", "id": id(1), "name": "HQ", "hasPart": [{"id": id(2)}]}),
+            ),
+            space(json!({"type": "room", "id": id(2), "name": "Lobby"})),
+            space(json!({"type": "access_control_zone", "id": id(5), "name": "Secure"})),
+            group.into(),
+        ]);
+
+        let report = ConsistencyReport::check(&tenant);
+        let found: Vec<_> = report
+            .one_sided
+            .iter()
+            .map(|one_sided| (one_sided.source.id, one_sided.field, one_sided.missing))
+            .collect();
+
+        assert_eq!(report.len(), 2, "{report}");
+        assert!(found.contains(&(id(1), "has_part", "is_part_of")));
+        assert!(found.contains(&(id(3), "includes_zone", "included_in")));
+    }
+
+    #[test]
+    fn either_side_can_be_missing() {
+        let tenant = Tenant::new(vec![
+            space(json!({"type": "building", "id": id(1), "name": "HQ"})),
+            space(
+                json!({"type": "room", "id": id(2), "name": "Lobby", "isPartOf": [{"id": id(1)}]}),
+            ),
+        ]);
+
+        let report = ConsistencyReport::check(&tenant);
+
+        assert_eq!(report.len(), 1, "{report}");
+        assert_eq!(report.one_sided[0].field, "is_part_of");
+        assert_eq!(report.one_sided[0].missing, "has_part");
+    }
+
+    #[test]
+    fn mirrored_relations_are_consistent() {
+        let tenant = Tenant::new(vec![
+            space(
+                json!({"type": "building", "id": id(1), "name": "HQ", "hasPart": [{"id": id(2)}]}),
+            ),
+            space(
+                json!({"type": "room", "id": id(2), "name": "Lobby", "isPartOf": [{"id": id(1)}]}),
+            ),
+            space(
+                json!({"type": "room", "id": id(3), "name": "Gone", "isPartOf": [{"id": id(9)}]}),
+            ),
+        ]);
+
+        assert!(ConsistencyReport::check(&tenant).is_empty());
+    }
+}
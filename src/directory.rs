@@ -0,0 +1,410 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    export,
+    handler::Handler,
+    models::{
+        agent::Agent,
+        collection::Collection,
+        event::Event,
+        space::{Space, SpaceType},
+        Entity, Relation,
+    },
+    spatial::SpatialTree,
+    tenant::Tenant,
+};
+
+/// Units leased by each occupant of a level
+type Units = HashMap<Uuid, Vec<Relation>>;
+
+/// Company or department leasing space on a level
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryEntry {
+    pub occupant: Relation,
+    /// Spaces and collections leased, as named on the lease
+    pub units: Vec<Relation>,
+}
+
+/// Occupants of one level, `level` is `None` for
+/// space leased outside any level, such as a whole building
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryLevel {
+    pub level: Option<Relation>,
+    pub level_number: Option<u32>,
+    pub entries: Vec<DirectoryEntry>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildingDirectory {
+    pub building: Relation,
+    pub levels: Vec<DirectoryLevel>,
+}
+
+/// Which company or department is on which level of
+/// every building, from the leases active on one date
+///
+/// Only companies and departments named as leasee are listed.
+/// Levels are ordered by `level_number`, occupants by name.
+#[derive(Serialize, Debug, Clone)]
+pub struct TenantDirectory {
+    pub on: DateTime<Utc>,
+    pub buildings: Vec<BuildingDirectory>,
+}
+
+impl TenantDirectory {
+    /// Load the tenant and build the directory for `on`
+    pub async fn load(
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        on: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+
+        Ok(Self::new(&tenant, on))
+    }
+
+    /// Build from a tenant snapshot
+    pub fn new(tenant: &Tenant, on: DateTime<Utc>) -> Self {
+        let tree = SpatialTree::new(tenant.spaces().cloned().collect());
+
+        let mut placed: HashMap<Uuid, HashMap<Option<Uuid>, Units>> = HashMap::new();
+        let mut occupants: HashMap<Uuid, Relation> = HashMap::new();
+
+        let leases = tenant.events().filter_map(|event| match event {
+            Event::Lease(lease) if lease.is_active_at(on) => Some(lease),
+            _ => None,
+        });
+
+        for lease in leases {
+            let leasees: Vec<Relation> = lease
+                .leasee
+                .iter()
+                .flatten()
+                .filter_map(|relation| match tenant.get(&relation.id) {
+                    Some(Entity::Agent(agent @ (Agent::Company(_) | Agent::Department(_)))) => {
+                        Some(Relation::to(agent))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            if leasees.is_empty() {
+                continue;
+            }
+
+            for target in lease.lease_of.iter().flatten() {
+                let unit = tenant.get(&target.id).map_or(target.clone(), Relation::to);
+
+                let spaces: Vec<Uuid> = match tenant.get(&target.id) {
+                    Some(Entity::Collection(collection)) => Self::included(collection),
+                    _ => vec![target.id],
+                };
+
+                for space in spaces {
+                    let Some(building) = tree.building_of(&space) else {
+                        continue;
+                    };
+
+                    let level = Self::level_of(&tree, &space).map(|level| level.id());
+
+                    for leasee in &leasees {
+                        occupants.insert(leasee.id, leasee.clone());
+
+                        let units = placed
+                            .entry(building.id())
+                            .or_default()
+                            .entry(level)
+                            .or_default()
+                            .entry(leasee.id)
+                            .or_default();
+
+                        if !units.iter().any(|u| u.id == unit.id) {
+                            units.push(unit.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut buildings: Vec<BuildingDirectory> = placed
+            .into_iter()
+            .filter_map(|(building, levels)| {
+                let building = tree.get(&building)?;
+
+                let mut levels: Vec<(Option<&Space>, DirectoryLevel)> = levels
+                    .into_iter()
+                    .map(|(level, entries)| {
+                        let level = level.and_then(|level| tree.get(&level));
+
+                        let mut entries: Vec<DirectoryEntry> = entries
+                            .into_iter()
+                            .filter_map(|(occupant, mut units)| {
+                                units.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+                                Some(DirectoryEntry {
+                                    occupant: occupants.get(&occupant)?.clone(),
+                                    units,
+                                })
+                            })
+                            .collect();
+                        entries.sort_by(|a, b| {
+                            a.occupant
+                                .name
+                                .cmp(&b.occupant.name)
+                                .then(a.occupant.id.cmp(&b.occupant.id))
+                        });
+
+                        let directory = DirectoryLevel {
+                            level: level.map(Relation::to),
+                            level_number: level.and_then(Space::level_number),
+                            entries,
+                        };
+
+                        (level, directory)
+                    })
+                    .collect();
+
+                levels.sort_by(|(a, _), (b, _)| match (a, b) {
+                    (Some(a), Some(b)) => SpatialTree::compare(a, b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                });
+
+                Some(BuildingDirectory {
+                    building: Relation::to(building),
+                    levels: levels.into_iter().map(|(_, level)| level).collect(),
+                })
+            })
+            .collect();
+
+        buildings.sort_by(|a, b| {
+            a.building
+                .name
+                .cmp(&b.building.name)
+                .then(a.building.id.cmp(&b.building.id))
+        });
+
+        Self { on, buildings }
+    }
+
+    /// Ids of the spaces included in a collection
+    fn included(collection: &Collection) -> Vec<Uuid> {
+        collection
+            .includes()
+            .iter()
+            .map(|relation| relation.id)
+            .collect()
+    }
+
+    /// Level a space is on, the space itself if it is a level
+    fn level_of<'a>(tree: &'a SpatialTree, id: &Uuid) -> Option<&'a Space> {
+        let space = tree.get(id)?;
+
+        if space.space_type() == SpaceType::Level {
+            return Some(space);
+        }
+
+        tree.ancestors(id)
+            .into_iter()
+            .find(|space| space.space_type() == SpaceType::Level)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Standalone HTML page with one table per building
+    pub fn to_html(&self) -> String {
+        let name = |relation: &Relation| {
+            export::html_text(relation.name.as_deref().unwrap_or(&relation.id.to_string()))
+        };
+
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Tenant directory</title>\n</head>\n<body>\n",
+        );
+
+        for building in &self.buildings {
+            html.push_str(&format!(
+                "<section>\n<h1>{}</h1>\n",
+                name(&building.building)
+            ));
+            html.push_str("<table>\n<tr><th>Level</th><th>Tenant</th><th>Spaces</th></tr>\n");
+
+            for level in &building.levels {
+                let level_name = level.level.as_ref().map(name).unwrap_or_default();
+
+                for entry in &level.entries {
+                    let units: Vec<String> = entry.units.iter().map(name).collect();
+
+                    html.push_str(&format!(
+                        "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                        level_name,
+                        name(&entry.occupant),
+                        units.join(", ")
+                    ));
+                }
+            }
+
+            html.push_str("</table>\n</section>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn at(month: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, 1, 0, 0, 0).unwrap()
+    }
+
+    fn entity(value: Value) -> Entity {
+        match value["type"].as_str().unwrap() {
+            "lease" => serde_json::from_value::<Event>(value).unwrap().into(),
+            "premises" => serde_json::from_value::<Collection>(value).unwrap().into(),
+            "company" | "department" | "person" => {
+                serde_json::from_value::<Agent>(value).unwrap().into()
+            }
+            _ => serde_json::from_value::<Space>(value).unwrap().into(),
+        }
+    }
+
+    fn lease(lease: u128, of: u128, leasee: u128, end: Option<DateTime<Utc>>) -> Entity {
+        entity(json!({
+            "type": "lease",
+            "id": id(lease),
+            "name": "Lease",
+            "start": at(1),
+            "end": end,
+            "leaseOf": [{"id": id(of)}],
+            "leasee": [{"id": id(leasee)}],
+        }))
+    }
+
+    fn directory() -> TenantDirectory {
+        let entities = vec![
+            entity(json!({
+                "type": "building",
+                "id": id(1),
+                "name": "Main",
+                "hasPart": [{"id": id(2)}, {"id": id(3)}],
+            })),
+            entity(json!({
+                "type": "level",
+                "id": id(2),
+                "name": "Second",
+                "levelNumber": 2,
+                "isPartOf": [{"id": id(1)}],
+                "hasPart": [{"id": id(4)}],
+            })),
+            entity(json!({
+                "type": "level",
+                "id": id(3),
+                "name": "First",
+                "levelNumber": 1,
+                "isPartOf": [{"id": id(1)}],
+                "hasPart": [{"id": id(5)}],
+            })),
+            entity(
+                json!({"type": "room", "id": id(4), "name": "Upper", "isPartOf": [{"id": id(2)}]}),
+            ),
+            entity(
+                json!({"type": "room", "id": id(5), "name": "Lower", "isPartOf": [{"id": id(3)}]}),
+            ),
+            entity(json!({
+                "type": "premises",
+                "id": id(6),
+                "name": "Offices <east>",
+                "includes": [{"id": id(4)}, {"id": id(5)}],
+            })),
+            entity(json!({"type": "company", "id": id(20), "name": "Acme & Co"})),
+            entity(json!({"type": "department", "id": id(21), "name": "Facilities"})),
+            entity(json!({"type": "person", "id": id(22), "name": "Jane Doe"})),
+            entity(json!({"type": "company", "id": id(23), "name": "Gone Ltd"})),
+            lease(10, 6, 20, None),
+            lease(11, 1, 21, None),
+            lease(12, 5, 22, None),
+            lease(13, 5, 23, Some(at(2))),
+        ];
+
+        TenantDirectory::new(&Tenant::new(entities), at(3))
+    }
+
+    fn occupants(level: &DirectoryLevel) -> Vec<&str> {
+        level
+            .entries
+            .iter()
+            .filter_map(|entry| entry.occupant.name.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn collections_are_listed_on_every_level_they_reach() {
+        let directory = directory();
+        let levels = &directory.buildings[0].levels;
+
+        assert_eq!(
+            levels
+                .iter()
+                .map(|level| level.level_number)
+                .collect::<Vec<_>>(),
+            [Some(1), Some(2), None]
+        );
+        assert_eq!(occupants(&levels[0]), ["Acme & Co"]);
+        assert_eq!(occupants(&levels[1]), ["Acme & Co"]);
+        assert_eq!(levels[0].entries[0].units[0].id, id(6));
+    }
+
+    #[test]
+    fn whole_building_leases_have_no_level() {
+        let directory = directory();
+        let level = directory.buildings[0].levels.last().unwrap();
+
+        assert!(level.level.is_none());
+        assert_eq!(occupants(level), ["Facilities"]);
+        assert_eq!(level.entries[0].units[0].id, id(1));
+    }
+
+    #[test]
+    fn persons_and_ended_leases_are_left_out() {
+        let directory = directory();
+
+        for level in &directory.buildings[0].levels {
+            let occupants = occupants(level);
+
+            assert!(!occupants.contains(&"Jane Doe"));
+            assert!(!occupants.contains(&"Gone Ltd"));
+        }
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        let html = directory().to_html();
+
+        assert!(html.contains("<td>Acme &amp; Co</td>"));
+        assert!(html.contains("Offices &lt;east&gt;"));
+        assert!(!html.contains("<east>"));
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    export,
+    handler::Handler,
+    metering::MeterTopology,
+    models::{
+        collection::{Collection, CollectionType},
+        reading::{self, Reading, Resolution},
+        space::{Space, SpaceType},
+        Relation,
+    },
+    spatial::SpatialTree,
+    tenant::Tenant,
+};
+
+/// Consumption of one scope and unit over one period
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Consumption {
+    pub scope: Relation,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub unit: String,
+    pub total: f64,
+    pub net_area: Option<f64>,
+    /// `total` per m² of `net_area`
+    pub intensity: Option<f64>,
+}
+
+/// Consumption per building, level, premises and real estate
+///
+/// A scope counts the physical meters located in it or in any
+/// space within it, skipping meters whose supplying meter is
+/// counted as well so consumption is not summed twice.
+#[derive(Serialize, Debug, Clone)]
+pub struct EnergyReport {
+    pub resolution: Resolution,
+    pub rows: Vec<Consumption>,
+}
+
+impl EnergyReport {
+    /// Load the tenant and the readings of every physical meter
+    /// with `from < timestamp <= to`, then build the report
+    pub async fn load(
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        resolution: Resolution,
+    ) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+        let mut readings = HashMap::new();
+
+        for meter in tenant.assets().filter(|asset| asset.is_meter()) {
+            if meter.is_virtual_meter() {
+                continue;
+            }
+
+            let id = meter.id();
+            readings.insert(id, handler.readings(client, token, &id, from, to).await?);
+        }
+
+        Ok(Self::new(&tenant, &readings, resolution))
+    }
+
+    /// Build from a tenant snapshot and readings by meter id
+    pub fn new(
+        tenant: &Tenant,
+        readings: &HashMap<Uuid, Vec<Reading>>,
+        resolution: Resolution,
+    ) -> Self {
+        let tree = SpatialTree::new(tenant.spaces().cloned().collect());
+        let topology = MeterTopology::new(tenant.assets().cloned().collect());
+
+        let mut scopes: Vec<(Relation, HashSet<Uuid>, Option<f64>)> = Vec::new();
+
+        for space_type in [SpaceType::Building, SpaceType::Level] {
+            let mut spaces: Vec<&Space> = tree
+                .spaces()
+                .filter(|space| space.space_type() == space_type)
+                .collect();
+            spaces.sort_by(|a, b| a.name().cmp(b.name()));
+
+            for space in spaces {
+                let ids = Self::within(&tree, [space.id()]);
+                scopes.push((Relation::to(space), ids, Self::net_area(&tree, &space.id())));
+            }
+        }
+
+        for collection_type in [CollectionType::Premises, CollectionType::RealEstate] {
+            let mut collections: Vec<&Collection> = tenant
+                .collections()
+                .filter(|collection| collection.collection_type() == collection_type)
+                .collect();
+            collections.sort_by(|a, b| a.name().cmp(b.name()));
+
+            for collection in collections {
+                let included: Vec<Uuid> = collection
+                    .includes()
+                    .iter()
+                    .map(|relation| relation.id)
+                    .collect();
+
+                let area = Self::outermost(&tree, &included)
+                    .iter()
+                    .filter_map(|id| Self::net_area(&tree, id))
+                    .fold(None, |sum: Option<f64>, area| {
+                        Some(sum.unwrap_or(0.) + area)
+                    });
+
+                scopes.push((
+                    Relation::to(collection),
+                    Self::within(&tree, included),
+                    area,
+                ));
+            }
+        }
+
+        let mut rows = Vec::new();
+
+        for (scope, spaces, net_area) in scopes {
+            let meters: HashSet<Uuid> = tenant
+                .assets()
+                .filter(|asset| asset.is_meter() && !asset.is_virtual_meter())
+                .filter(|asset| {
+                    asset
+                        .located_in()
+                        .iter()
+                        .any(|relation| spaces.contains(&relation.id))
+                })
+                .map(|asset| asset.id())
+                .collect();
+
+            let mut totals: BTreeMap<(DateTime<Utc>, String), (DateTime<Utc>, f64)> =
+                BTreeMap::new();
+
+            for meter in &meters {
+                let supplied = topology
+                    .suppliers(meter)
+                    .iter()
+                    .any(|supplier| meters.contains(supplier));
+
+                if supplied {
+                    continue;
+                }
+
+                let Some(readings) = readings.get(meter) else {
+                    continue;
+                };
+
+                for bucket in reading::aggregate(readings, resolution) {
+                    let total = totals
+                        .entry((bucket.start, bucket.unit))
+                        .or_insert((bucket.end, 0.));
+                    total.1 += bucket.sum;
+                }
+            }
+
+            for ((start, unit), (end, total)) in totals {
+                rows.push(Consumption {
+                    scope: scope.clone(),
+                    start,
+                    end,
+                    unit,
+                    total,
+                    net_area,
+                    intensity: net_area.filter(|area| *area > 0.).map(|area| total / area),
+                });
+            }
+        }
+
+        Self { resolution, rows }
+    }
+
+    /// Provided spaces and every space within them
+    fn within(tree: &SpatialTree, ids: impl IntoIterator<Item = Uuid>) -> HashSet<Uuid> {
+        let mut within = HashSet::new();
+
+        for id in ids {
+            within.insert(id);
+            within.extend(tree.descendants(&id).iter().map(|space| space.id()));
+        }
+
+        within
+    }
+
+    /// Spaces not within another of the provided spaces,
+    /// so areas are not counted twice
+    fn outermost(tree: &SpatialTree, ids: &[Uuid]) -> Vec<Uuid> {
+        ids.iter()
+            .filter(|id| {
+                !tree
+                    .ancestors(id)
+                    .iter()
+                    .any(|ancestor| ids.contains(&ancestor.id()))
+            })
+            .copied()
+            .collect::<HashSet<Uuid>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Net area of a space, or the sum of its parts if not set
+    fn net_area(tree: &SpatialTree, id: &Uuid) -> Option<f64> {
+        Self::net_area_within(tree, id, &mut HashSet::new())
+    }
+
+    fn net_area_within(tree: &SpatialTree, id: &Uuid, visited: &mut HashSet<Uuid>) -> Option<f64> {
+        let space = tree.get(id)?;
+
+        if !visited.insert(*id) {
+            return None;
+        }
+
+        if let Some(area) = space.area().and_then(|area| area.net_area) {
+            return Some(area);
+        }
+
+        tree.children(id)
+            .iter()
+            .filter_map(|child| Self::net_area_within(tree, &child.id(), visited))
+            .fold(None, |sum, area| Some(sum.unwrap_or(0.) + area))
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = export::csv_row(&[
+            "scope_id",
+            "scope_type",
+            "scope_name",
+            "start",
+            "end",
+            "unit",
+            "total",
+            "net_area",
+            "intensity",
+        ]);
+
+        for row in &self.rows {
+            csv.push_str(&export::csv_row(&[
+                row.scope.id.to_string(),
+                row.scope.rtype.clone().unwrap_or_default(),
+                row.scope.name.clone().unwrap_or_default(),
+                row.start.to_rfc3339(),
+                row.end.to_rfc3339(),
+                row.unit.clone(),
+                row.total.to_string(),
+                row.net_area
+                    .map(|area| area.to_string())
+                    .unwrap_or_default(),
+                row.intensity.map(|i| i.to_string()).unwrap_or_default(),
+            ]));
+        }
+
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, TimeZone};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::{asset::Asset, Entity};
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn space(space_type: &str, space: u128, part_of: &[u128], net_area: Option<f64>) -> Entity {
+        let is_part_of: Vec<Value> = part_of
+            .iter()
+            .map(|id| json!({"id": Uuid::from_u128(*id)}))
+            .collect();
+        let area = net_area.map(|net_area| {
+            json!({"id": id(1000 + space), "type": "area", "name": "Area", "netArea": net_area})
+        });
+
+        serde_json::from_value::<Space>(json!({
+            "type": space_type,
+            "id": id(space),
+            "name": format!("Space {space}"),
+            "isPartOf": is_part_of,
+            "area": area,
+        }))
+        .unwrap()
+        .into()
+    }
+
+    fn meter(meter: u128, located_in: u128, feeds: &[u128]) -> Entity {
+        let feeds: Vec<Value> = feeds.iter().map(|fed| json!({"id": id(*fed)})).collect();
+
+        serde_json::from_value::<Asset>(json!({
+            "type": "electrical_meter",
+            "id": id(meter),
+            "name": format!("Meter {meter}"),
+            "locatedIn": [{"id": id(located_in)}],
+            "feeds": feeds,
+        }))
+        .unwrap()
+        .into()
+    }
+
+    fn premises(premises: u128, includes: &[u128]) -> Entity {
+        let includes: Vec<Value> = includes
+            .iter()
+            .map(|id| json!({"id": Uuid::from_u128(*id)}))
+            .collect();
+
+        serde_json::from_value::<Collection>(json!({
+            "type": "premises",
+            "id": id(premises),
+            "name": format!("Premises {premises}"),
+            "includes": includes,
+        }))
+        .unwrap()
+        .into()
+    }
+
+    fn reading(day: u32, value: f64) -> Reading {
+        serde_json::from_value(json!({
+            "timestamp": Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+            "value": value,
+            "unit": "kWh",
+        }))
+        .unwrap()
+    }
+
+    fn tenant() -> Tenant {
+        Tenant::new(vec![
+            space("building", 1, &[], None),
+            space("level", 2, &[1], Some(100.)),
+            space("level", 3, &[1], Some(50.)),
+            meter(10, 1, &[11]),
+            meter(11, 2, &[]),
+            premises(20, &[1, 2]),
+        ])
+    }
+
+    #[test]
+    fn supplied_meters_are_not_summed_twice() {
+        let readings = HashMap::from([
+            (id(10), vec![reading(2, 30.)]),
+            (id(11), vec![reading(2, 10.)]),
+        ]);
+
+        let report = EnergyReport::new(&tenant(), &readings, Resolution::Daily);
+        let total = |scope: u128| {
+            report
+                .rows
+                .iter()
+                .find(|row| row.scope.id == id(scope))
+                .map(|row| (row.start.day(), row.total, row.net_area))
+        };
+
+        assert_eq!(total(1), Some((1, 30., Some(150.))));
+        assert_eq!(total(2), Some((1, 10., Some(100.))));
+        assert_eq!(total(3), None);
+        assert_eq!(
+            report
+                .rows
+                .iter()
+                .find(|row| row.scope.id == id(2))
+                .unwrap()
+                .intensity,
+            Some(0.1)
+        );
+    }
+
+    #[test]
+    fn collection_area_counts_nested_spaces_once() {
+        let report = EnergyReport::new(
+            &tenant(),
+            &HashMap::from([(id(10), vec![reading(2, 30.)])]),
+            Resolution::Daily,
+        );
+
+        let premises = report
+            .rows
+            .iter()
+            .find(|row| row.scope.id == id(20))
+            .unwrap();
+
+        assert_eq!(premises.net_area, Some(150.));
+        assert_eq!(premises.total, 30.);
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
+use uuid::Uuid;
 use wrapi::http::StatusCode;
 
 use wrapi::error::Error as WrapiError;
@@ -18,6 +19,24 @@ pub enum Error {
     InternalError(Body),
     /// Unknown/unsupported
     Unknown(String),
+    /// Relation not allowed between the provided entities
+    InvalidRelation(String),
+    /// Entity is not part of the loaded tenant
+    NotInTenant(Uuid),
+    /// Entity can not be deleted while the listed entities depend on it
+    HasDependents(Vec<Uuid>),
+    /// Space is not bookable
+    NotBookable(Uuid),
+    /// Booking overlaps the listed bookings of the same space
+    BookingConflict(Vec<Uuid>),
+    /// Booking or booking change is not valid
+    InvalidBooking(String),
+    /// Recurrence rule is malformed or not supported
+    InvalidRecurrence(String),
+    /// iCalendar data is malformed
+    InvalidCalendar(String),
+    /// Lease is missing terms needed for the calculation
+    InvalidLease(String),
     ClientError(String),
     ClientDecodeError(String),
 }
@@ -44,6 +63,15 @@ impl fmt::Display for Error {
             | Error::NotFound(body)
             | Error::InternalError(body) => write!(f, "{}", body.message),
             Error::Unknown(reason) => write!(f, "Unknown: {}", reason),
+            Error::InvalidRelation(reason) => write!(f, "Invalid relation: {reason}"),
+            Error::NotInTenant(id) => write!(f, "{id} is not part of the tenant"),
+            Error::HasDependents(ids) => write!(f, "Has {} dependent entities", ids.len()),
+            Error::NotBookable(id) => write!(f, "Space {id} is not bookable"),
+            Error::BookingConflict(ids) => write!(f, "Conflicts with {} bookings", ids.len()),
+            Error::InvalidBooking(reason) => write!(f, "Invalid booking: {reason}"),
+            Error::InvalidRecurrence(reason) => write!(f, "Invalid recurrence: {reason}"),
+            Error::InvalidCalendar(reason) => write!(f, "Invalid calendar: {reason}"),
+            Error::InvalidLease(reason) => write!(f, "Invalid lease: {reason}"),
             Error::ClientError(reason) => write!(f, "Client: {reason}"),
             Error::ClientDecodeError(reason) => write!(f, "Client decode error: {}", reason),
         }
@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+
+/// Quote a CSV field if it contains a separator, quote or newline
+pub(crate) fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Join fields into a CSV line ending in CRLF
+pub(crate) fn csv_row<S: AsRef<str>>(fields: &[S]) -> String {
+    let fields: Vec<Cow<'_, str>> = fields.iter().map(|f| csv_field(f.as_ref())).collect();
+
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Escape an iCalendar TEXT value
+pub(crate) fn ical_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold an iCalendar content line at 75 octets and end it in CRLF
+pub(crate) fn ical_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            len = 1;
+        }
+
+        folded.push(c);
+        len += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Format a UTC iCalendar DATE-TIME
+pub(crate) fn ical_datetime(value: DateTime<Utc>) -> String {
+    value.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Parse an iCalendar DATE-TIME or DATE, floating
+/// times and dates are taken as UTC
+pub(crate) fn parse_ical_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim_end_matches('Z');
+
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y%m%d").map(|date| date.and_time(Default::default()))
+        })
+        .ok()
+        .map(|value| value.and_utc())
+}
+
+/// Escape text for HTML element content and quoted attributes
+pub(crate) fn html_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
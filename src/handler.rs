@@ -1,5 +1,6 @@
 use std::fmt;
 
+use chrono::{DateTime, Duration, Utc};
 use serde::de::DeserializeOwned;
 use uuid::Uuid;
 use wrapi::{request::Request, reqwest::Client};
@@ -7,14 +8,16 @@ use wrapi::{request::Request, reqwest::Client};
 use crate::{
     error::Error,
     models::{
-        agent::{Agent, AgentId, AgentType, Key, NewAgent},
-        asset::{Asset, AssetType, NewAsset},
+        agent::{Agent, AgentId, AgentType, Key, NewAgent, NewKey, RevokeKey},
+        asset::{Asset, AssetId, AssetType, NewAsset},
         auth::{Auth, AuthReq},
-        collection::{Collection, CollectionType, NewCollection},
+        collection::{Collection, CollectionId, CollectionType, NewCollection},
         event::{Event, EventId, EventType, NewEvent},
-        information::{Information, InformationType, NewInformation},
-        space::{NewSpace, Space, SpaceType},
-        Id,
+        information::{Information, InformationId, InformationType, NewInformation},
+        point::{NewPoint, Point, PointId, PointType},
+        reading::{self, NewReadings, Reading, ReadingBucket, ReadingQuery, Resolution},
+        space::{NewSpace, Space, SpaceId, SpaceType},
+        Entity, Id, Resource,
     },
 };
 
@@ -98,6 +101,75 @@ impl Handler {
         self.send(client, Id { id: *id }, token).await
     }
 
+    /// Issue a key to a person
+    pub async fn create_person_key(
+        &self,
+        client: &Client,
+        token: &str,
+        person: &Uuid,
+        key: Key,
+    ) -> Result<(), Error> {
+        let payload = NewKey {
+            person: *person,
+            key,
+        };
+
+        let _ = self.send_opt(client, payload, token).await?;
+
+        Ok(())
+    }
+
+    /// Revoke a key of a person
+    pub async fn revoke_person_key(
+        &self,
+        client: &Client,
+        token: &str,
+        person: &Uuid,
+        key: &Key,
+    ) -> Result<(), Error> {
+        let payload = RevokeKey {
+            person: *person,
+            provider: key.provider.clone(),
+            key: key.key.clone(),
+        };
+
+        let _ = self.send_opt(client, payload, token).await?;
+
+        Ok(())
+    }
+
+    /// Replace a key of a person with a new key of the same type,
+    /// provider and validity window
+    ///
+    /// The new key is issued before the old one is revoked, and
+    /// revoked again if revoking the old key fails.
+    pub async fn rotate_person_key(
+        &self,
+        client: &Client,
+        token: &str,
+        person: &Uuid,
+        old: &Key,
+        key: &str,
+    ) -> Result<Key, Error> {
+        let new = Key {
+            key: key.into(),
+            ..old.clone()
+        };
+
+        self.create_person_key(client, token, person, new.clone())
+            .await?;
+
+        if let Err(err) = self.revoke_person_key(client, token, person, old).await {
+            if let Err(rollback) = self.revoke_person_key(client, token, person, &new).await {
+                tracing::error!("Failed to revoke rotated key: {rollback}");
+            }
+
+            return Err(err);
+        }
+
+        Ok(new)
+    }
+
     /// Get events of provided type
     pub async fn events(
         &self,
@@ -125,6 +197,18 @@ impl Handler {
         self.send(client, Id { id: *id }, token).await
     }
 
+    /// Replace an event
+    pub async fn replace_event(
+        &self,
+        client: &Client,
+        token: &str,
+        payload: Event,
+    ) -> Result<(), Error> {
+        let _ = self.send_opt(client, payload, token).await?;
+
+        Ok(())
+    }
+
     pub async fn delete_event(&self, client: &Client, token: &str, id: &Uuid) -> Result<(), Error> {
         let _ = self
             .send_opt(client, EventId(Id { id: *id }), token)
@@ -172,6 +256,15 @@ impl Handler {
         Ok(())
     }
 
+    /// Delete a space
+    pub async fn delete_space(&self, client: &Client, token: &str, id: &Uuid) -> Result<(), Error> {
+        let _ = self
+            .send_opt(client, SpaceId(Id { id: *id }), token)
+            .await?;
+
+        Ok(())
+    }
+
     /// Get assets of provided type
     pub async fn assets(
         &self,
@@ -211,6 +304,71 @@ impl Handler {
         Ok(())
     }
 
+    /// Delete an asset
+    pub async fn delete_asset(&self, client: &Client, token: &str, id: &Uuid) -> Result<(), Error> {
+        let _ = self
+            .send_opt(client, AssetId(Id { id: *id }), token)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Push a batch of readings for a meter
+    pub async fn push_readings(
+        &self,
+        client: &Client,
+        token: &str,
+        meter: &Uuid,
+        readings: Vec<Reading>,
+    ) -> Result<(), Error> {
+        let payload = NewReadings {
+            meter: *meter,
+            readings,
+        };
+
+        let _ = self.send_opt(client, payload, token).await?;
+
+        Ok(())
+    }
+
+    /// Get readings for a meter with `from < timestamp <= to`,
+    /// the readings closing the intervals between `from` and `to`
+    pub async fn readings(
+        &self,
+        client: &Client,
+        token: &str,
+        meter: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Reading>, Error> {
+        let payload = ReadingQuery {
+            meter: *meter,
+            from,
+            to: to.checked_add_signed(Duration::seconds(1)).unwrap_or(to),
+        };
+
+        let mut readings: Vec<Reading> = self.send(client, payload, token).await?;
+        readings.retain(|reading| from < reading.timestamp && reading.timestamp <= to);
+
+        Ok(readings)
+    }
+
+    /// Get readings for a meter with `from < timestamp <= to`
+    /// aggregated into buckets
+    pub async fn aggregated_readings(
+        &self,
+        client: &Client,
+        token: &str,
+        meter: &Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        resolution: Resolution,
+    ) -> Result<Vec<ReadingBucket>, Error> {
+        let readings = self.readings(client, token, meter, from, to).await?;
+
+        Ok(reading::aggregate(&readings, resolution))
+    }
+
     /// Get collections of provided type
     pub async fn collections(
         &self,
@@ -255,6 +413,20 @@ impl Handler {
         Ok(())
     }
 
+    /// Delete collection
+    pub async fn delete_collection(
+        &self,
+        client: &Client,
+        token: &str,
+        id: &Uuid,
+    ) -> Result<(), Error> {
+        let _ = self
+            .send_opt(client, CollectionId(Id { id: *id }), token)
+            .await?;
+
+        Ok(())
+    }
+
     /// Get information of provided type
     pub async fn all_information(
         &self,
@@ -299,6 +471,105 @@ impl Handler {
         Ok(())
     }
 
+    /// Delete information
+    pub async fn delete_information(
+        &self,
+        client: &Client,
+        token: &str,
+        id: &Uuid,
+    ) -> Result<(), Error> {
+        let _ = self
+            .send_opt(client, InformationId(Id { id: *id }), token)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get points of provided type
+    pub async fn points(
+        &self,
+        client: &Client,
+        token: &str,
+        point_type: PointType,
+    ) -> Result<Vec<Point>, Error> {
+        self.send(client, point_type, token).await
+    }
+
+    /// Create a point
+    pub async fn create_point(
+        &self,
+        client: &Client,
+        token: &str,
+        payload: NewPoint,
+    ) -> Result<Uuid, Error> {
+        let res = self.send(client, payload, token).await?;
+
+        Ok(res.id)
+    }
+
+    /// Get point by id
+    pub async fn point(&self, client: &Client, token: &str, id: &Uuid) -> Result<Point, Error> {
+        self.send(client, Id { id: *id }, token).await
+    }
+
+    /// Replace a point
+    pub async fn replace_point(
+        &self,
+        client: &Client,
+        token: &str,
+        payload: Point,
+    ) -> Result<(), Error> {
+        let _ = self.send_opt(client, payload, token).await?;
+
+        Ok(())
+    }
+
+    /// Delete a point
+    pub async fn delete_point(&self, client: &Client, token: &str, id: &Uuid) -> Result<(), Error> {
+        let _ = self
+            .send_opt(client, PointId(Id { id: *id }), token)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replace any entity through the endpoint of its kind
+    pub async fn replace(
+        &self,
+        client: &Client,
+        token: &str,
+        payload: Entity,
+    ) -> Result<(), Error> {
+        match payload {
+            Entity::Agent(agent) => self.replace_agent(client, token, agent).await,
+            Entity::Asset(asset) => self.replace_asset(client, token, asset).await,
+            Entity::Collection(collection) => {
+                self.replace_collection(client, token, collection).await
+            }
+            Entity::Event(event) => self.replace_event(client, token, event).await,
+            Entity::Information(information) => {
+                self.replace_information(client, token, information).await
+            }
+            Entity::Point(point) => self.replace_point(client, token, point).await,
+            Entity::Space(space) => self.replace_space(client, token, space).await,
+        }
+    }
+
+    /// Delete any entity through the endpoint of its kind
+    pub async fn delete(&self, client: &Client, token: &str, entity: &Entity) -> Result<(), Error> {
+        let id = entity.id();
+
+        match entity {
+            Entity::Agent(_) => self.delete_agent(client, id, token).await,
+            Entity::Asset(_) => self.delete_asset(client, token, &id).await,
+            Entity::Collection(_) => self.delete_collection(client, token, &id).await,
+            Entity::Event(_) => self.delete_event(client, token, &id).await,
+            Entity::Information(_) => self.delete_information(client, token, &id).await,
+            Entity::Point(_) => self.delete_point(client, token, &id).await,
+            Entity::Space(_) => self.delete_space(client, token, &id).await,
+        }
+    }
+
     // TODO: Check if we have a valid token
     // and if not, try to get one
     //
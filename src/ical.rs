@@ -0,0 +1,1328 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    booking::{self, RoomBooking},
+    error::Error,
+    export,
+    handler::Handler,
+    lease::LeaseAnalytics,
+    models::{
+        agent::Agent,
+        event::{Booking, Event, EventType, Lease, NewEvent},
+        Id, Identifier, Relation,
+    },
+    recurrence,
+    tenant::Tenant,
+};
+
+/// Integration of the identifier holding the iCalendar UID
+pub const ICAL_INTEGRATION: &str = "ical";
+
+/// Booking or lease as an iCalendar `VEVENT`
+///
+/// Relations are written as `urn:uuid:` values with the cached
+/// name in `CN` and type in `X-TYPE`. The room of a booking and
+/// the spaces of a lease are written to `LOCATION` and
+/// `X-SRENITY-LOCATION`, the booker to `ORGANIZER`, leasees to
+/// `ATTENDEE`. Events from other calendars, with only a `LOCATION`
+/// text and a `mailto:` organizer, are matched against the tenant
+/// by [`VEvent::resolve`]. Times with a `TZID` are converted to
+/// UTC, floating times are taken as UTC. A `TZID` is read as an
+/// IANA name, else from a `VTIMEZONE` of the calendar, else as a
+/// Windows zone name. Dates are read as the start of the day,
+/// and an all-day booking without `DTEND` lasts that day.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VEvent {
+    pub uid: String,
+    /// Id of the event in Srenity, if exported from it
+    pub id: Option<Uuid>,
+    pub event_type: EventType,
+    pub name: String,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    /// Identifiers other than the UID
+    pub identifiers: Vec<Identifier>,
+    /// Room of a booking, spaces of a lease
+    pub location: Vec<Relation>,
+    /// `LOCATION` text, the names of the spaces
+    pub location_text: Option<String>,
+    /// Booker of a booking
+    pub organizer: Option<Relation>,
+    /// `ORGANIZER` address other than a `urn:uuid:`, such as `mailto:`
+    pub organizer_address: Option<String>,
+    /// `CN` of an organizer given by address
+    pub organizer_name: Option<String>,
+    /// Leasees of a lease
+    pub attendees: Vec<Relation>,
+    pub leasor: Vec<Relation>,
+    /// Lease a booking is made under
+    pub lease: Option<Relation>,
+}
+
+impl VEvent {
+    /// Convert a booking or a lease
+    pub fn from_event(event: &Event) -> Option<Self> {
+        let (id, identifiers) = match event {
+            Event::Booking(booking) => (booking.id, &booking.identifiers),
+            Event::Lease(lease) => (lease.id, &lease.identifiers),
+            _ => return None,
+        };
+
+        let identifiers = identifiers.clone().unwrap_or_default();
+        let uid = identifiers
+            .iter()
+            .find(|identifier| identifier.integration == ICAL_INTEGRATION)
+            .map(|identifier| identifier.external_id.clone())
+            .unwrap_or_else(|| format!("{id}@srenity"));
+
+        let mut vevent = Self {
+            uid,
+            id: Some(id),
+            event_type: event.event_type(),
+            name: event.name().into(),
+            start: Default::default(),
+            end: None,
+            identifiers: identifiers
+                .into_iter()
+                .filter(|identifier| identifier.integration != ICAL_INTEGRATION)
+                .collect(),
+            location: Vec::new(),
+            location_text: None,
+            organizer: None,
+            organizer_address: None,
+            organizer_name: None,
+            attendees: Vec::new(),
+            leasor: Vec::new(),
+            lease: None,
+        };
+
+        match event {
+            Event::Booking(booking) => {
+                vevent.start = booking.start;
+                vevent.end = Some(booking.end);
+                vevent.location = booking.room.iter().cloned().collect();
+                vevent.organizer = booking.booked_by.clone();
+                vevent.lease = booking.lease.clone();
+            }
+            Event::Lease(lease) => {
+                vevent.start = lease.start;
+                vevent.end = lease.end;
+                vevent.location = lease.lease_of.clone().unwrap_or_default();
+                vevent.attendees = lease.leasee.clone().unwrap_or_default();
+                vevent.leasor = lease.leasor.clone().unwrap_or_default();
+            }
+            _ => {}
+        }
+
+        let names: Vec<&str> = vevent
+            .location
+            .iter()
+            .filter_map(|relation| relation.name.as_deref())
+            .collect();
+        vevent.location_text = (!names.is_empty()).then(|| names.join(", "));
+
+        Some(vevent)
+    }
+
+    fn all_identifiers(&self) -> Vec<Identifier> {
+        let mut identifiers = self.identifiers.clone();
+        identifiers.push(Identifier {
+            integration: ICAL_INTEGRATION.into(),
+            external_id: self.uid.clone(),
+        });
+        identifiers
+    }
+
+    /// Event with the id read from `X-SRENITY-ID`, `None` if the
+    /// id is missing or a booking has no end
+    pub fn to_event(&self) -> Option<Event> {
+        let id = self.id?;
+
+        match self.event_type {
+            EventType::Booking => Some(Event::Booking(Booking {
+                id,
+                name: self.name.clone(),
+                start: self.start,
+                end: self.end?,
+                identifiers: Some(self.all_identifiers()),
+                booked_by: self.organizer.clone(),
+                lease: self.lease.clone(),
+                room: self.location.first().cloned(),
+            })),
+            EventType::Lease => Some(Event::Lease(Lease {
+                id,
+                name: self.name.clone(),
+                start: self.start,
+                end: self.end,
+                identifiers: Some(self.all_identifiers()),
+                leasee: non_empty(&self.attendees),
+                leasor: non_empty(&self.leasor),
+                lease_of: non_empty(&self.location),
+                annual_rent: None,
+                currency: None,
+                payment_frequency: None,
+                indexation: None,
+                deposit: None,
+                notice_period_months: None,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Booking to make, fails if the event is not a booking
+    /// or has no end, room or lease
+    pub fn to_room_booking(&self) -> Result<RoomBooking, Error> {
+        if self.event_type != EventType::Booking {
+            return Err(invalid(format!("{} is not a booking", self.uid)));
+        }
+
+        let missing = |what: &str| invalid(format!("no {what} found for booking {}", self.uid));
+
+        Ok(RoomBooking {
+            name: self.name.clone(),
+            room: self.location.first().ok_or_else(|| missing("room"))?.id,
+            start: self.start,
+            end: self
+                .end
+                .ok_or_else(|| invalid(format!("booking {} has no DTEND", self.uid)))?,
+            lease: self.lease.as_ref().ok_or_else(|| missing("lease"))?.id,
+            booked_by: self.organizer.as_ref().map(|relation| relation.id),
+            identifiers: Some(self.all_identifiers()),
+        })
+    }
+
+    /// Event to create, fails if a booking has no end, room or lease
+    pub fn to_new_event(&self) -> Result<NewEvent, Error> {
+        let ids = |relations: &[Relation]| {
+            (!relations.is_empty()).then(|| {
+                relations
+                    .iter()
+                    .map(|relation| Id { id: relation.id })
+                    .collect()
+            })
+        };
+
+        match self.event_type {
+            EventType::Booking => Ok(self.to_room_booking()?.into()),
+            EventType::Lease => Ok(NewEvent::Lease {
+                name: self.name.clone(),
+                start: self.start.to_rfc3339(),
+                end: self.end.map(|end| end.to_rfc3339()),
+                identifiers: Some(self.all_identifiers()),
+                leasee: ids(&self.attendees),
+                leasor: ids(&self.leasor),
+                lease_of: ids(&self.location),
+                annual_rent: None,
+                currency: None,
+                payment_frequency: None,
+                indexation: None,
+                deposit: None,
+                notice_period_months: None,
+            }),
+            event_type => Err(invalid(format!(
+                "{} events can not be imported",
+                event_type.to_string()
+            ))),
+        }
+    }
+
+    /// Whether a booking lacks a room, organizer or lease
+    /// that [`VEvent::resolve`] could fill in
+    fn is_unresolved(&self) -> bool {
+        self.event_type == EventType::Booking
+            && ((self.location.is_empty() && self.location_text.is_some())
+                || (self.organizer.is_none() && self.organizer_address.is_some())
+                || self.lease.is_none())
+    }
+
+    /// Fill in the room, organizer and lease of a booking
+    /// from another calendar
+    ///
+    /// The room is the bookable space named in `LOCATION`, the
+    /// organizer the person holding the `ORGANIZER` address as an
+    /// identifier or else named as its `CN`, the lease the lease of
+    /// the room active at the start. Each is only set on a single
+    /// match, and relations already read are kept.
+    pub fn resolve(&mut self, tenant: &Tenant, leases: &LeaseAnalytics) {
+        if self.event_type != EventType::Booking {
+            return;
+        }
+
+        if self.location.is_empty() {
+            let names: Vec<&str> = self
+                .location_text
+                .iter()
+                .flat_map(|text| text.split([',', ';']))
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect();
+
+            let rooms: Vec<_> = tenant
+                .spaces()
+                .filter(|space| space.is_bookable())
+                .filter(|space| {
+                    names
+                        .iter()
+                        .any(|name| space.name().eq_ignore_ascii_case(name))
+                })
+                .collect();
+
+            if let [room] = rooms.as_slice() {
+                self.location = vec![Relation::to(*room)];
+            }
+        }
+
+        if self.organizer.is_none() {
+            let address = self.organizer_address.as_deref().map(|address| {
+                address
+                    .get(..7)
+                    .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+                    .map_or(address, |_| &address[7..])
+            });
+
+            let persons = || {
+                tenant.agents().filter_map(|agent| match agent {
+                    Agent::Person(person) => Some((agent, person)),
+                    _ => None,
+                })
+            };
+
+            let by_address: Vec<&Agent> =
+                persons()
+                    .filter(|(_, person)| {
+                        address.is_some_and(|address| {
+                            person.identifiers.iter().flatten().any(|identifier| {
+                                identifier.external_id.eq_ignore_ascii_case(address)
+                            })
+                        })
+                    })
+                    .map(|(agent, _)| agent)
+                    .collect();
+
+            let by_name: Vec<&Agent> = persons()
+                .filter(|(_, person)| self.organizer_name.as_deref() == Some(person.name.as_str()))
+                .map(|(agent, _)| agent)
+                .collect();
+
+            match (by_address.as_slice(), by_name.as_slice()) {
+                ([person], _) | ([], [person]) => self.organizer = Some(Relation::to(*person)),
+                _ => {}
+            }
+        }
+
+        if self.lease.is_none() {
+            if let Some(room) = self.location.first() {
+                if let [lease] = leases.leases_of(&room.id, self.start).as_slice() {
+                    self.lease = Some(Relation::to(&Event::Lease((*lease).clone())));
+                }
+            }
+        }
+    }
+
+    fn write(&self, ics: &mut String, stamp: &str) {
+        let mut line = |line: String| ics.push_str(&export::ical_line(&line));
+
+        line("BEGIN:VEVENT".into());
+        line(format!("UID:{}", export::ical_text(&self.uid)));
+        line(format!("DTSTAMP:{stamp}"));
+        line(format!("DTSTART:{}", export::ical_datetime(self.start)));
+
+        if let Some(end) = self.end {
+            line(format!("DTEND:{}", export::ical_datetime(end)));
+        }
+
+        line(format!("SUMMARY:{}", export::ical_text(&self.name)));
+        line(format!(
+            "CATEGORIES:{}",
+            self.event_type.to_string().to_uppercase()
+        ));
+
+        if let Some(location) = &self.location_text {
+            line(format!("LOCATION:{}", export::ical_text(location)));
+        }
+
+        for relation in &self.location {
+            line(format!("X-SRENITY-LOCATION{}", relation_value(relation)));
+        }
+
+        if let Some(organizer) = &self.organizer {
+            line(format!("ORGANIZER{}", relation_value(organizer)));
+        }
+
+        for attendee in &self.attendees {
+            line(format!("ATTENDEE{}", relation_value(attendee)));
+        }
+
+        for leasor in &self.leasor {
+            line(format!("X-SRENITY-LEASOR{}", relation_value(leasor)));
+        }
+
+        if let Some(lease) = &self.lease {
+            line(format!("X-SRENITY-LEASE{}", relation_value(lease)));
+        }
+
+        if let Some(id) = self.id {
+            line(format!("X-SRENITY-ID:{id}"));
+        }
+
+        for identifier in &self.identifiers {
+            line(format!(
+                "X-SRENITY-IDENTIFIER;X-INTEGRATION={}:{}",
+                param_value(&identifier.integration),
+                export::ical_text(&identifier.external_id)
+            ));
+        }
+
+        line("END:VEVENT".into());
+    }
+}
+
+fn non_empty(relations: &[Relation]) -> Option<Vec<Relation>> {
+    (!relations.is_empty()).then(|| relations.to_vec())
+}
+
+/// Quote a parameter value, dropping characters it can not hold
+fn param_value(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| *c != '"' && !c.is_control())
+        .collect();
+
+    if value.contains([';', ':', ',']) {
+        format!("\"{value}\"")
+    } else {
+        value
+    }
+}
+
+/// `;CN=name;X-TYPE=type:urn:uuid:id` of a relation
+fn relation_value(relation: &Relation) -> String {
+    let mut value = String::new();
+
+    if let Some(name) = &relation.name {
+        value.push_str(&format!(";CN={}", param_value(name)));
+    }
+
+    if let Some(rtype) = &relation.rtype {
+        value.push_str(&format!(";X-TYPE={}", param_value(rtype)));
+    }
+
+    format!("{value}:urn:uuid:{}", relation.id)
+}
+
+/// Calendar holding the events
+pub fn to_ics(events: &[VEvent], stamp: DateTime<Utc>) -> String {
+    let stamp = export::ical_datetime(stamp);
+    let mut ics = String::new();
+
+    ics.push_str(&export::ical_line("BEGIN:VCALENDAR"));
+    ics.push_str(&export::ical_line("VERSION:2.0"));
+    ics.push_str(&export::ical_line("PRODID:-//srenity-rs//events//EN"));
+
+    for event in events {
+        event.write(&mut ics, &stamp);
+    }
+
+    ics.push_str(&export::ical_line("END:VCALENDAR"));
+
+    ics
+}
+
+/// Content line split into name, parameters and value
+struct Property {
+    name: String,
+    params: HashMap<String, String>,
+    value: String,
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidCalendar(reason.into())
+}
+
+/// Join folded lines
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in ics.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.into()),
+        }
+    }
+
+    lines
+}
+
+fn parse_property(line: &str) -> Result<Property, Error> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut value = None;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => parts.push(std::mem::take(&mut current)),
+            ':' if !quoted => {
+                parts.push(std::mem::take(&mut current));
+                value = Some(&line[index + 1..]);
+                break;
+            }
+            _ => current.push(c),
+        }
+    }
+
+    let value = value.ok_or_else(|| invalid(format!("missing value in {line}")))?;
+    let mut parts = parts.into_iter();
+    let name = parts.next().unwrap_or_default().to_ascii_uppercase();
+
+    let params = parts
+        .filter_map(|param| {
+            param
+                .split_once('=')
+                .map(|(key, value)| (key.to_ascii_uppercase(), value.to_string()))
+        })
+        .collect();
+
+    Ok(Property {
+        name,
+        params,
+        value: value.into(),
+    })
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Relation from a `urn:uuid:` value, `None` for other addresses
+fn parse_relation(property: &Property) -> Option<Relation> {
+    let id = property
+        .value
+        .strip_prefix("urn:uuid:")
+        .and_then(|id| Uuid::parse_str(id).ok())?;
+
+    Some(Relation {
+        id,
+        rtype: property.params.get("X-TYPE").cloned(),
+        name: property.params.get("CN").cloned(),
+    })
+}
+
+/// Whether a property holds a DATE rather than a DATE-TIME
+fn is_date(property: &Property) -> bool {
+    property
+        .params
+        .get("VALUE")
+        .is_some_and(|value| value.eq_ignore_ascii_case("DATE"))
+        || !property.value.contains('T')
+}
+
+/// `STANDARD` or `DAYLIGHT` part of a `VTIMEZONE`
+struct Observance {
+    /// Local time it first takes effect
+    start: NaiveDateTime,
+    /// Seconds east of UTC before and after it takes effect
+    offset_from: i32,
+    offset_to: i32,
+    /// Month, week of the month and weekday of a yearly `RRULE`,
+    /// weeks below zero count from the end of the month
+    rule: Option<(u32, i8, Weekday)>,
+}
+
+impl Observance {
+    fn parse(properties: &[Property]) -> Option<Self> {
+        let get = |name: &str| {
+            properties
+                .iter()
+                .find(|property| property.name == name)
+                .map(|property| property.value.as_str())
+        };
+
+        Some(Self {
+            start: NaiveDateTime::parse_from_str(get("DTSTART")?, "%Y%m%dT%H%M%S").ok()?,
+            offset_from: parse_offset(get("TZOFFSETFROM")?)?,
+            offset_to: parse_offset(get("TZOFFSETTO")?)?,
+            rule: match get("RRULE") {
+                Some(rule) => Some(parse_yearly_rule(rule)?),
+                None => None,
+            },
+        })
+    }
+
+    /// Latest local time at or before `time` it took effect
+    fn onset(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let Some((month, week, weekday)) = self.rule else {
+            return (self.start <= time).then_some(self.start);
+        };
+
+        [time.year(), time.year() - 1]
+            .into_iter()
+            .filter_map(|year| nth_weekday(year, month, week, weekday))
+            .map(|date| date.and_time(self.start.time()))
+            .find(|onset| *onset <= time && *onset >= self.start)
+    }
+}
+
+/// `+hhmm` or `+hhmmss` as seconds east of UTC
+fn parse_offset(value: &str) -> Option<i32> {
+    let (sign, digits) = match value.split_at_checked(1)? {
+        ("+", digits) => (1, digits),
+        ("-", digits) => (-1, digits),
+        _ => return None,
+    };
+
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let part = |from: usize| {
+        digits
+            .get(from..from + 2)
+            .map_or(Some(0), |part| part.parse().ok())
+    };
+
+    Some(sign * (part(0)? * 3600 + part(2)? * 60 + part(4)?))
+}
+
+/// `FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU` of an observance
+fn parse_yearly_rule(rule: &str) -> Option<(u32, i8, Weekday)> {
+    let mut yearly = false;
+    let mut month = None;
+    let mut day = None;
+
+    for part in rule.split(';') {
+        match part.split_once('=')? {
+            ("FREQ", freq) => yearly = freq == "YEARLY",
+            ("BYMONTH", value) => month = value.parse().ok(),
+            ("BYDAY", value) => {
+                let (week, weekday) = value.split_at_checked(value.len().checked_sub(2)?)?;
+                day = Some((week.parse().ok()?, recurrence::parse_weekday(weekday).ok()?));
+            }
+            _ => {}
+        }
+    }
+
+    let (week, weekday) = day?;
+    yearly.then_some((month?, week, weekday))
+}
+
+/// Date of the `week`th `weekday` of the month, from the end if negative
+fn nth_weekday(year: i32, month: u32, week: i8, weekday: Weekday) -> Option<NaiveDate> {
+    if week > 0 {
+        return NaiveDate::from_weekday_of_month_opt(year, month, weekday, week as u8);
+    }
+
+    let last = NaiveDate::from_ymd_opt(year, month, 1)?
+        .checked_add_months(Months::new(1))?
+        .pred_opt()?;
+    let back = (last.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    let weeks = u64::from(week.unsigned_abs().checked_sub(1)?);
+
+    last.checked_sub_days(Days::new(u64::from(back) + 7 * weeks))
+}
+
+/// IANA zone of a common Windows zone name
+fn windows_zone(name: &str) -> Option<Tz> {
+    let iana = match name {
+        "UTC" | "Coordinated Universal Time" => "UTC",
+        "GMT Standard Time" => "Europe/London",
+        "Greenwich Standard Time" => "Atlantic/Reykjavik",
+        "W. Europe Standard Time" => "Europe/Berlin",
+        "Central Europe Standard Time" => "Europe/Budapest",
+        "Central European Standard Time" => "Europe/Warsaw",
+        "Romance Standard Time" => "Europe/Paris",
+        "E. Europe Standard Time" => "Europe/Chisinau",
+        "FLE Standard Time" => "Europe/Kiev",
+        "GTB Standard Time" => "Europe/Bucharest",
+        "Russian Standard Time" => "Europe/Moscow",
+        "South Africa Standard Time" => "Africa/Johannesburg",
+        "Arabian Standard Time" => "Asia/Dubai",
+        "India Standard Time" => "Asia/Kolkata",
+        "China Standard Time" => "Asia/Shanghai",
+        "Singapore Standard Time" => "Asia/Singapore",
+        "Tokyo Standard Time" => "Asia/Tokyo",
+        "AUS Eastern Standard Time" => "Australia/Sydney",
+        "New Zealand Standard Time" => "Pacific/Auckland",
+        "Atlantic Standard Time" => "America/Halifax",
+        "Eastern Standard Time" => "America/New_York",
+        "Central Standard Time" => "America/Chicago",
+        "Mountain Standard Time" => "America/Denver",
+        "US Mountain Standard Time" => "America/Phoenix",
+        "Pacific Standard Time" => "America/Los_Angeles",
+        "Alaskan Standard Time" => "America/Anchorage",
+        "Hawaiian Standard Time" => "Pacific/Honolulu",
+        "E. South America Standard Time" => "America/Sao_Paulo",
+        _ => return None,
+    };
+
+    iana.parse().ok()
+}
+
+/// Observances of the `VTIMEZONE`s of a calendar by `TZID`
+#[derive(Default)]
+struct TimeZones(HashMap<String, Vec<Observance>>);
+
+impl TimeZones {
+    /// UTC time of a local time in the zone `tzid`,
+    /// `None` if the zone is unknown
+    fn to_utc(&self, tzid: &str, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+        if let Ok(tz) = tzid.parse::<Tz>() {
+            return Some(recurrence::to_utc(&tz, local));
+        }
+
+        if let Some(observances) = self.0.get(tzid) {
+            let offset = observances
+                .iter()
+                .filter_map(|observance| Some((observance.onset(local)?, observance.offset_to)))
+                .max_by_key(|(onset, _)| *onset)
+                .map(|(_, offset)| offset)
+                .or_else(|| {
+                    observances
+                        .iter()
+                        .min_by_key(|observance| observance.start)
+                        .map(|observance| observance.offset_from)
+                })?;
+
+            return Some((local - Duration::seconds(offset.into())).and_utc());
+        }
+
+        windows_zone(tzid).map(|tz| recurrence::to_utc(&tz, local))
+    }
+}
+
+/// DATE or DATE-TIME, local to the `TZID` if any
+fn parse_time(property: &Property, zones: &TimeZones) -> Result<DateTime<Utc>, Error> {
+    let error = || invalid(format!("invalid {} {}", property.name, property.value));
+
+    if property.value.ends_with('Z') {
+        return export::parse_ical_datetime(&property.value).ok_or_else(error);
+    }
+
+    let local = if is_date(property) {
+        NaiveDate::parse_from_str(&property.value, "%Y%m%d")
+            .map(|date| date.and_time(NaiveTime::MIN))
+    } else {
+        NaiveDateTime::parse_from_str(&property.value, "%Y%m%dT%H%M%S")
+    }
+    .map_err(|_| error())?;
+
+    let Some(tzid) = property.params.get("TZID") else {
+        return Ok(local.and_utc());
+    };
+
+    zones
+        .to_utc(tzid.trim_matches('"'), local)
+        .ok_or_else(|| invalid(format!("unknown TZID {tzid} on {}", property.name)))
+}
+
+/// Read bookings and leases from a calendar, and the events
+/// that could not be read with the reason
+///
+/// Events without `CATEGORIES:LEASE` are read as bookings.
+/// Only properties of the `VEVENT` itself are read, not those
+/// of components nested in it such as `VALARM`. Fails only if
+/// a line can not be read or components are not closed in order.
+pub fn parse_ics(ics: &str) -> Result<(Vec<VEvent>, Vec<SkippedEvent>), Error> {
+    let mut vevents = Vec::new();
+    let mut zones = TimeZones::default();
+    let mut components: Vec<String> = Vec::new();
+    let mut current: Vec<Property> = Vec::new();
+    let mut observance: Vec<Property> = Vec::new();
+    let mut zone: (Option<String>, Vec<Observance>) = Default::default();
+
+    for line in unfold(ics) {
+        let property = parse_property(&line)?;
+
+        match property.name.as_str() {
+            "BEGIN" => components.push(property.value.to_ascii_uppercase()),
+            "END" => {
+                let component = property.value.to_ascii_uppercase();
+
+                if components.pop().as_ref() != Some(&component) {
+                    return Err(invalid(format!("END:{component} without BEGIN")));
+                }
+
+                match component.as_str() {
+                    "VEVENT" => vevents.push(std::mem::take(&mut current)),
+                    "STANDARD" | "DAYLIGHT" => zone
+                        .1
+                        .extend(Observance::parse(&std::mem::take(&mut observance))),
+                    "VTIMEZONE" => {
+                        if let (Some(tzid), observances) = std::mem::take(&mut zone) {
+                            if !observances.is_empty() {
+                                zones.0.insert(tzid, observances);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => match components.last().map(String::as_str) {
+                Some("VEVENT") => current.push(property),
+                Some("VTIMEZONE") if property.name == "TZID" => zone.0 = Some(property.value),
+                Some("STANDARD" | "DAYLIGHT") => observance.push(property),
+                _ => {}
+            },
+        }
+    }
+
+    if let Some(component) = components.last() {
+        return Err(invalid(format!("{component} without END")));
+    }
+
+    let mut events = Vec::new();
+    let mut skipped = Vec::new();
+
+    for properties in vevents {
+        let value = |name: &str| {
+            properties
+                .iter()
+                .find(|property| property.name == name)
+                .map(|property| unescape(&property.value))
+                .unwrap_or_default()
+        };
+
+        match parse_vevent(&properties, &zones) {
+            Ok(vevent) => events.push(vevent),
+            Err(error) => skipped.push(SkippedEvent {
+                uid: value("UID"),
+                name: value("SUMMARY"),
+                reason: error.to_string(),
+            }),
+        }
+    }
+
+    Ok((events, skipped))
+}
+
+fn parse_vevent(properties: &[Property], zones: &TimeZones) -> Result<VEvent, Error> {
+    let mut uid = None;
+    let mut start = None;
+    let mut all_day = false;
+    let mut vevent = VEvent {
+        uid: String::new(),
+        id: None,
+        event_type: EventType::Booking,
+        name: String::new(),
+        start: Default::default(),
+        end: None,
+        identifiers: Vec::new(),
+        location: Vec::new(),
+        location_text: None,
+        organizer: None,
+        organizer_address: None,
+        organizer_name: None,
+        attendees: Vec::new(),
+        leasor: Vec::new(),
+        lease: None,
+    };
+
+    for property in properties {
+        match property.name.as_str() {
+            "UID" => uid = Some(unescape(&property.value)),
+            "DTSTART" => {
+                start = Some(parse_time(property, zones)?);
+                all_day = is_date(property);
+            }
+            "DTEND" => vevent.end = Some(parse_time(property, zones)?),
+            "SUMMARY" => vevent.name = unescape(&property.value),
+            "CATEGORIES" => {
+                let lease = property
+                    .value
+                    .split(',')
+                    .any(|category| category.eq_ignore_ascii_case("lease"));
+
+                if lease {
+                    vevent.event_type = EventType::Lease;
+                }
+            }
+            "X-SRENITY-ID" => vevent.id = Uuid::parse_str(&property.value).ok(),
+            "LOCATION" => vevent.location_text = Some(unescape(&property.value)),
+            "X-SRENITY-LOCATION" => vevent.location.extend(parse_relation(property)),
+            "ORGANIZER" => {
+                vevent.organizer = parse_relation(property);
+
+                if vevent.organizer.is_none() {
+                    vevent.organizer_address = Some(property.value.clone());
+                    vevent.organizer_name = property.params.get("CN").cloned();
+                }
+            }
+            "ATTENDEE" => vevent.attendees.extend(parse_relation(property)),
+            "X-SRENITY-LEASOR" => vevent.leasor.extend(parse_relation(property)),
+            "X-SRENITY-LEASE" => vevent.lease = parse_relation(property),
+            "X-SRENITY-IDENTIFIER" => {
+                if let Some(integration) = property.params.get("X-INTEGRATION") {
+                    vevent.identifiers.push(Identifier {
+                        integration: integration.clone(),
+                        external_id: unescape(&property.value),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    vevent.uid = uid.ok_or_else(|| invalid("VEVENT without UID"))?;
+    vevent.start =
+        start.ok_or_else(|| invalid(format!("VEVENT {} without DTSTART", vevent.uid)))?;
+
+    if all_day && vevent.end.is_none() && vevent.event_type == EventType::Booking {
+        vevent.end = vevent.start.checked_add_days(Days::new(1));
+    }
+
+    Ok(vevent)
+}
+
+/// Calendar of the bookings of a room
+pub async fn room_feed(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    room: &Uuid,
+    stamp: DateTime<Utc>,
+) -> Result<String, Error> {
+    let mut bookings: Vec<Booking> = booking::bookings(handler, client, token)
+        .await?
+        .into_iter()
+        .filter(|booking| booking.room.as_ref().map(|room| room.id) == Some(*room))
+        .collect();
+    bookings.sort_by_key(|booking| booking.start);
+
+    let events: Vec<VEvent> = bookings
+        .into_iter()
+        .filter_map(|booking| VEvent::from_event(&Event::Booking(booking)))
+        .collect();
+
+    Ok(to_ics(&events, stamp))
+}
+
+/// Event of a calendar that was not imported
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedEvent {
+    pub uid: String,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Outcome of importing a calendar
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    /// Ids of the created events
+    pub created: Vec<Uuid>,
+    pub skipped: Vec<SkippedEvent>,
+}
+
+/// Create the bookings and leases of a calendar
+///
+/// Bookings are made with [`booking::book_room`]. Events that
+/// can not be read or whose UID is already stored on an event of
+/// the same type are skipped, as are bookings without an end,
+/// room or lease, of a space that is not bookable, or
+/// overlapping other bookings.
+/// Bookings from other calendars are resolved against the tenant,
+/// loaded once on the first such booking.
+pub async fn import(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    ics: &str,
+) -> Result<ImportReport, Error> {
+    let mut known: HashMap<EventType, Vec<String>> = HashMap::new();
+    let mut tenant: Option<(Tenant, LeaseAnalytics)> = None;
+    let (vevents, skipped) = parse_ics(ics)?;
+    let mut report = ImportReport {
+        skipped,
+        ..Default::default()
+    };
+
+    for mut vevent in vevents {
+        let uids = match known.entry(vevent.event_type) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                handler
+                    .events(client, token, vevent.event_type)
+                    .await?
+                    .iter()
+                    .filter_map(VEvent::from_event)
+                    .map(|event| event.uid)
+                    .collect(),
+            ),
+        };
+
+        if uids.contains(&vevent.uid) {
+            report.skipped.push(SkippedEvent {
+                uid: vevent.uid,
+                name: vevent.name,
+                reason: "already imported".into(),
+            });
+            continue;
+        }
+
+        if vevent.is_unresolved() {
+            if tenant.is_none() {
+                let loaded = Tenant::load(handler, client, token).await?;
+                let leases = LeaseAnalytics::new(&loaded);
+                tenant = Some((loaded, leases));
+            }
+
+            if let Some((tenant, leases)) = &tenant {
+                vevent.resolve(tenant, leases);
+            }
+        }
+
+        let created = match vevent.event_type {
+            EventType::Booking => match vevent.to_room_booking() {
+                Ok(booking) => booking::book_room(handler, client, token, booking).await,
+                Err(error) => Err(error),
+            },
+            _ => match vevent.to_new_event() {
+                Ok(payload) => handler.create_event(client, token, payload).await,
+                Err(error) => Err(error),
+            },
+        };
+
+        match created {
+            Ok(id) => {
+                report.created.push(id);
+                uids.push(vevent.uid);
+            }
+            Err(
+                error @ (Error::InvalidCalendar(_)
+                | Error::InvalidBooking(_)
+                | Error::NotBookable(_)
+                | Error::BookingConflict(_)),
+            ) => report.skipped.push(SkippedEvent {
+                uid: vevent.uid,
+                name: vevent.name,
+                reason: error.to_string(),
+            }),
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::{space::Space, Entity};
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    fn tenant() -> Tenant {
+        let room = |id: u128, name: &str| -> Entity {
+            serde_json::from_value::<Space>(json!({
+                "type": "room",
+                "id": Uuid::from_u128(id),
+                "name": name,
+                "bookable": true,
+            }))
+            .unwrap()
+            .into()
+        };
+
+        let person = serde_json::from_value::<Agent>(json!({
+            "type": "person",
+            "id": Uuid::from_u128(3),
+            "name": "Jane Doe",
+            "identifiers": [{"integration": "email", "externalId": "jane@example.com"}],
+        }))
+        .unwrap();
+
+        let lease = serde_json::from_value::<Event>(json!({
+            "type": "lease",
+            "id": Uuid::from_u128(4),
+            "name": "Floor lease",
+            "start": at(0) - chrono::Duration::days(30),
+            "leaseOf": [{"id": Uuid::from_u128(1)}],
+        }))
+        .unwrap();
+
+        Tenant::new(vec![
+            room(1, "Aurora"),
+            room(2, "Borealis"),
+            person.into(),
+            lease.into(),
+        ])
+    }
+
+    fn booking(ics_lines: &str) -> VEvent {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:ext-1\r\n\
+             DTSTART:20240301T090000Z\r\nDTEND:20240301T100000Z\r\n\
+             SUMMARY:Planning\r\n{ics_lines}END:VEVENT\r\nEND:VCALENDAR\r\n"
+        );
+
+        parse_ics(&ics).unwrap().0.remove(0)
+    }
+
+    #[test]
+    fn foreign_booking_is_resolved_from_location_and_organizer() {
+        let tenant = tenant();
+        let leases = LeaseAnalytics::new(&tenant);
+
+        let mut vevent = booking(
+            "LOCATION:aurora\\, 2nd floor\r\n\
+             ORGANIZER;CN=Someone:MAILTO:Jane@Example.com\r\n",
+        );
+        assert_eq!(vevent.location_text.as_deref(), Some("aurora, 2nd floor"));
+        assert!(vevent.to_new_event().is_err());
+
+        vevent.resolve(&tenant, &leases);
+
+        assert_eq!(vevent.location[0].id, Uuid::from_u128(1));
+        assert_eq!(vevent.organizer.as_ref().unwrap().id, Uuid::from_u128(3));
+        assert_eq!(vevent.lease.as_ref().unwrap().id, Uuid::from_u128(4));
+        assert!(vevent.to_new_event().is_ok());
+
+        let booking = vevent.to_room_booking().unwrap();
+        assert_eq!(booking.room, Uuid::from_u128(1));
+        assert_eq!(booking.booked_by, Some(Uuid::from_u128(3)));
+        assert_eq!((booking.start, booking.end), (at(9), at(10)));
+    }
+
+    #[test]
+    fn organizer_falls_back_to_name() {
+        let tenant = tenant();
+        let leases = LeaseAnalytics::new(&tenant);
+
+        let mut vevent = booking("ORGANIZER;CN=Jane Doe:mailto:jd@elsewhere.org\r\n");
+        vevent.resolve(&tenant, &leases);
+
+        assert_eq!(vevent.organizer.unwrap().id, Uuid::from_u128(3));
+    }
+
+    #[test]
+    fn booking_without_lease_is_rejected() {
+        let tenant = tenant();
+        let leases = LeaseAnalytics::new(&tenant);
+
+        let mut vevent = booking("LOCATION:Borealis\r\n");
+        vevent.resolve(&tenant, &leases);
+
+        assert_eq!(vevent.location[0].id, Uuid::from_u128(2));
+        assert!(vevent.lease.is_none());
+        assert!(matches!(
+            vevent.to_new_event(),
+            Err(Error::InvalidCalendar(_))
+        ));
+    }
+
+    #[test]
+    fn export_parses_back_to_the_same_event() {
+        let event = serde_json::from_value::<Event>(json!({
+            "type": "booking",
+            "id": Uuid::from_u128(7),
+            "name": "Sync; weekly, team\nnotes",
+            "start": at(9),
+            "end": at(10),
+            "identifiers": [
+                {"integration": "ical", "externalId": "abc@example.com"},
+                {"integration": "crm", "externalId": "42"},
+            ],
+            "bookedBy": {"id": Uuid::from_u128(3), "type": "person", "name": "Doe, Jane"},
+            "room": {
+                "id": Uuid::from_u128(1),
+                "type": "room",
+                "name": "Aurora: the long room at the end of the hall past the kitchen",
+            },
+            "lease": {"id": Uuid::from_u128(4)},
+        }))
+        .unwrap();
+
+        let ics = to_ics(&[VEvent::from_event(&event).unwrap()], at(0));
+
+        for line in ics.split_terminator("\r\n") {
+            assert!(line.len() <= 75, "{line}");
+        }
+
+        let (parsed, skipped) = parse_ics(&ics).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].uid, "abc@example.com");
+        assert_eq!(
+            parsed[0].location_text.as_deref(),
+            Some("Aurora: the long room at the end of the hall past the kitchen")
+        );
+        assert!(parsed[0].to_new_event().is_ok());
+
+        let Some(Event::Booking(booking)) = parsed[0].to_event() else {
+            panic!("not a booking");
+        };
+        let Event::Booking(original) = event else {
+            unreachable!();
+        };
+
+        assert_eq!(booking.id, original.id);
+        assert_eq!(booking.name, original.name);
+        assert_eq!(booking.start, original.start);
+        assert_eq!(booking.end, original.end);
+        assert_eq!(
+            booking.lease.map(|lease| lease.id),
+            Some(Uuid::from_u128(4))
+        );
+
+        let relation = |relation: Option<Relation>| {
+            relation.map(|relation| (relation.id, relation.rtype, relation.name))
+        };
+        assert_eq!(relation(booking.booked_by), relation(original.booked_by));
+        assert_eq!(relation(booking.room), relation(original.room));
+
+        let identifiers = |identifiers: Option<Vec<Identifier>>| {
+            let mut identifiers: Vec<_> = identifiers
+                .into_iter()
+                .flatten()
+                .map(|identifier| (identifier.integration, identifier.external_id))
+                .collect();
+            identifiers.sort();
+            identifiers
+        };
+        assert_eq!(
+            identifiers(booking.identifiers),
+            identifiers(original.identifiers)
+        );
+    }
+
+    #[test]
+    fn local_times_are_converted_from_tzid() {
+        let ics = "BEGIN:VEVENT\r\nUID:tz\r\n\
+                   DTSTART;TZID=Europe/Stockholm:20240301T090000\r\n\
+                   DTEND;TZID=\"America/New_York\":20240701T090000\r\n\
+                   END:VEVENT\r\n";
+        let vevent = parse_ics(ics).unwrap().0.remove(0);
+
+        assert_eq!(vevent.start, at(8));
+        assert_eq!(
+            vevent.end,
+            Some(Utc.with_ymd_and_hms(2024, 7, 1, 13, 0, 0).unwrap())
+        );
+
+        // Skipped by the clocks going forward, read as 02:30+01:00
+        let ics = "BEGIN:VEVENT\r\nUID:gap\r\n\
+                   DTSTART;TZID=Europe/Stockholm:20240331T023000\r\n\
+                   END:VEVENT\r\n";
+        let vevent = parse_ics(ics).unwrap().0.remove(0);
+
+        assert_eq!(
+            vevent.start,
+            Utc.with_ymd_and_hms(2024, 3, 31, 1, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn tzid_is_read_from_vtimezone_or_windows_name() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\nUID:outlook\r\nSUMMARY:Review\r\n\
+                   DTSTART;TZID=W. Europe Standard Time:20240701T090000\r\n\
+                   DTEND;TZID=Custom:20240701T100000\r\nEND:VEVENT\r\n\
+                   BEGIN:VTIMEZONE\r\nTZID:Custom\r\n\
+                   BEGIN:STANDARD\r\nDTSTART:16011028T030000\r\n\
+                   RRULE:FREQ=YEARLY;BYDAY=-1SU;BYMONTH=10\r\n\
+                   TZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nEND:STANDARD\r\n\
+                   BEGIN:DAYLIGHT\r\nDTSTART:16010325T020000\r\n\
+                   RRULE:FREQ=YEARLY;BYDAY=-1SU;BYMONTH=3\r\n\
+                   TZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nEND:DAYLIGHT\r\n\
+                   END:VTIMEZONE\r\n\
+                   BEGIN:VEVENT\r\nUID:winter\r\n\
+                   DTSTART;TZID=Custom:20240331T013000\r\nEND:VEVENT\r\n\
+                   BEGIN:VEVENT\r\nUID:mars\r\nSUMMARY:Landing\r\n\
+                   DTSTART;TZID=Mars/Olympus_Mons:20240301T090000\r\n\
+                   END:VEVENT\r\nEND:VCALENDAR\r\n";
+        let (events, skipped) = parse_ics(ics).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].start,
+            Utc.with_ymd_and_hms(2024, 7, 1, 7, 0, 0).unwrap()
+        );
+        assert_eq!(
+            events[0].end,
+            Some(Utc.with_ymd_and_hms(2024, 7, 1, 8, 0, 0).unwrap())
+        );
+        assert_eq!(
+            events[1].start,
+            Utc.with_ymd_and_hms(2024, 3, 31, 0, 30, 0).unwrap()
+        );
+
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            (skipped[0].uid.as_str(), skipped[0].name.as_str()),
+            ("mars", "Landing")
+        );
+        assert!(skipped[0].reason.contains("Mars/Olympus_Mons"));
+    }
+
+    #[test]
+    fn alarm_properties_are_not_read() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:alarm\r\n\
+                   DTSTART:20240301T090000Z\r\nSUMMARY:Planning\r\n\
+                   BEGIN:VALARM\r\nACTION:DISPLAY\r\nSUMMARY:Reminder\r\n\
+                   DTSTART:20240301T084500Z\r\nEND:VALARM\r\n\
+                   DTEND:20240301T100000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let vevent = parse_ics(ics).unwrap().0.remove(0);
+
+        assert_eq!(vevent.name, "Planning");
+        assert_eq!(vevent.start, at(9));
+        assert_eq!(vevent.end, Some(at(10)));
+
+        let ics = "BEGIN:VEVENT\r\nUID:x\r\nBEGIN:VALARM\r\nEND:VEVENT\r\n";
+        assert!(matches!(parse_ics(ics), Err(Error::InvalidCalendar(_))));
+    }
+
+    #[test]
+    fn all_day_booking_lasts_the_day() {
+        let ics = "BEGIN:VEVENT\r\nUID:day\r\n\
+                   DTSTART;VALUE=DATE:20240301\r\n\
+                   END:VEVENT\r\n";
+        let vevent = parse_ics(ics).unwrap().0.remove(0);
+
+        assert_eq!(vevent.start, at(0));
+        assert_eq!(vevent.end, Some(at(0) + chrono::Duration::days(1)));
+
+        let ics = "BEGIN:VEVENT\r\nUID:day\r\n\
+                   DTSTART;VALUE=DATE;TZID=Europe/Stockholm:20240301\r\n\
+                   DTEND;VALUE=DATE;TZID=Europe/Stockholm:20240302\r\n\
+                   END:VEVENT\r\n";
+        let vevent = parse_ics(ics).unwrap().0.remove(0);
+
+        assert_eq!(vevent.start, at(0) - chrono::Duration::hours(1));
+        assert_eq!(vevent.end, Some(at(23)));
+    }
+}
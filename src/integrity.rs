@@ -0,0 +1,215 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{Relation, Resource},
+    tenant::Tenant,
+};
+
+/// Entity with relations pointing at ids that do not exist
+#[derive(Serialize, Debug, Clone)]
+pub struct DanglingEntity {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub rtype: String,
+    pub name: String,
+    /// Broken relations by field name
+    pub fields: BTreeMap<&'static str, Vec<Relation>>,
+}
+
+/// Every dangling relation in a tenant, grouped
+/// by source entity and relation field
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub entities: Vec<DanglingEntity>,
+}
+
+impl IntegrityReport {
+    /// Check every relation of every entity in `tenant`
+    pub fn check(tenant: &Tenant) -> Self {
+        let entities = tenant
+            .entities()
+            .iter()
+            .filter_map(|entity| {
+                let fields: BTreeMap<&'static str, Vec<Relation>> = entity
+                    .relations()
+                    .into_iter()
+                    .filter_map(|(field, relations)| {
+                        let dangling: Vec<Relation> = relations
+                            .iter()
+                            .filter(|relation| !tenant.contains(&relation.id))
+                            .cloned()
+                            .collect();
+
+                        (!dangling.is_empty()).then_some((field, dangling))
+                    })
+                    .collect();
+
+                (!fields.is_empty()).then(|| DanglingEntity {
+                    id: entity.id(),
+                    rtype: entity.rtype(),
+                    name: entity.name().to_string(),
+                    fields,
+                })
+            })
+            .collect();
+
+        Self { entities }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Number of dangling relations
+    pub fn len(&self) -> usize {
+        self.entities
+            .iter()
+            .flat_map(|entity| entity.fields.values())
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Remove the dangling relations through the replace
+    /// endpoints, returns the ids of the updated entities
+    ///
+    /// `tenant` must be the snapshot the report was made from.
+    pub async fn repair(
+        &self,
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        tenant: &Tenant,
+    ) -> Result<Vec<Uuid>, Error> {
+        let mut repaired = Vec::new();
+
+        for dangling in &self.entities {
+            let Some(mut entity) = tenant.get(&dangling.id).cloned() else {
+                continue;
+            };
+
+            let mut removed = 0;
+
+            for (name, mut field) in entity.relation_fields() {
+                let Some(broken) = dangling.fields.get(name) else {
+                    continue;
+                };
+
+                removed += field.retain(|relation| !broken.iter().any(|b| b.id == relation.id));
+            }
+
+            if removed == 0 {
+                continue;
+            }
+
+            tracing::info!(
+                "Removing {} dangling relation(s) from {} {}",
+                removed,
+                dangling.rtype,
+                dangling.id
+            );
+
+            handler.replace(client, token, entity).await?;
+            repaired.push(dangling.id);
+        }
+
+        Ok(repaired)
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entity in &self.entities {
+            writeln!(f, "{} {} ({})", entity.rtype, entity.name, entity.id)?;
+
+            for (field, relations) in &entity.fields {
+                for relation in relations {
+                    write!(f, "  {field}: {}", relation.id)?;
+
+                    if let Some(name) = &relation.name {
+                        write!(f, " ({name})")?;
+                    }
+
+                    writeln!(f)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::{event::Event, space::Space};
+
+    #[test]
+    fn dangling_relations_are_grouped_by_field() {
+        let building = serde_json::from_value::<Space>(json!({
+            "type": "building",
+            "id": Uuid::from_u128(1),
+            "name": "HQ",
+            "hasPart": [{"id": Uuid::from_u128(9), "name": "Gone"}],
+        }))
+        .unwrap();
+
+        let booking = serde_json::from_value::<Event>(json!({
+            "type": "booking",
+            "id": Uuid::from_u128(2),
+            "name": "Planning",
+            "start": "2024-01-01T09:00:00Z",
+            "end": "2024-01-01T10:00:00Z",
+            "room": {"id": Uuid::from_u128(1)},
+            "bookedBy": {"id": Uuid::from_u128(8)},
+        }))
+        .unwrap();
+
+        let report = IntegrityReport::check(&Tenant::new(vec![building.into(), booking.into()]));
+
+        assert_eq!(report.len(), 2);
+        assert_eq!(report.entities.len(), 2);
+        for entity in &report.entities {
+            let (field, relations) = entity.fields.iter().next().unwrap();
+            match entity.id.as_u128() {
+                1 => assert_eq!((*field, relations[0].id), ("has_part", Uuid::from_u128(9))),
+                2 => assert_eq!((*field, relations[0].id), ("booked_by", Uuid::from_u128(8))),
+                id => panic!("unexpected entity {id}"),
+            }
+        }
+        assert!(report
+            .to_string()
+            .contains("  has_part: 00000000-0000-0000-0000-000000000009 (Gone)"));
+    }
+
+    #[test]
+    fn complete_tenant_has_no_dangling_relations() {
+        let building = serde_json::from_value::<Space>(json!({
+            "type": "building",
+            "id": Uuid::from_u128(1),
+            "name": "HQ",
+            "hasPart": [{"id": Uuid::from_u128(2)}],
+        }))
+        .unwrap();
+
+        let level = serde_json::from_value::<Space>(json!({
+            "type": "level",
+            "id": Uuid::from_u128(2),
+            "name": "Ground",
+            "isPartOf": [{"id": Uuid::from_u128(1)}],
+        }))
+        .unwrap();
+
+        let report = IntegrityReport::check(&Tenant::new(vec![building.into(), level.into()]));
+
+        assert!(report.is_empty());
+        assert_eq!(report.len(), 0);
+    }
+}
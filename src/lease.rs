@@ -0,0 +1,710 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+};
+
+use chrono::{DateTime, Days, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    export,
+    handler::Handler,
+    models::{
+        collection::{Collection, CollectionType},
+        event::{Event, Lease},
+        space::{Space, SpaceType},
+        Relation,
+    },
+    spatial::SpatialTree,
+    tenant::Tenant,
+};
+
+/// Lease ending within the horizon
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaseExpiry {
+    pub lease: Relation,
+    pub end: DateTime<Utc>,
+    /// Whole days from `as_of` to `end`
+    pub days_left: i64,
+    pub leasee: Vec<Relation>,
+    pub lease_of: Vec<Relation>,
+}
+
+/// Room, premises or apartment without an active lease
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Vacancy {
+    pub unit: Relation,
+    pub building: Option<Relation>,
+    pub rentable_area: Option<f64>,
+}
+
+/// Leased versus rentable area of a building
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildingOccupancy {
+    pub building: Relation,
+    pub rentable_area: Option<f64>,
+    pub leased_area: f64,
+    /// `leased_area` per m² of `rentable_area`
+    pub occupancy: Option<f64>,
+}
+
+/// Occupancy of every building on one date
+#[derive(Serialize, Debug, Clone)]
+pub struct OccupancyTable {
+    pub on: DateTime<Utc>,
+    pub rows: Vec<BuildingOccupancy>,
+}
+
+/// Leases with the spaces and collections they are for
+///
+/// A lease of a space covers every space within it. A lease of
+/// a premises or apartment covers the spaces it includes.
+#[derive(Debug, Clone, Default)]
+pub struct LeaseAnalytics {
+    tree: SpatialTree,
+    collections: Vec<Collection>,
+    leases: Vec<Lease>,
+}
+
+impl LeaseAnalytics {
+    /// Load the tenant and collect its leases
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+
+        Ok(Self::new(&tenant))
+    }
+
+    /// Build from a tenant snapshot
+    pub fn new(tenant: &Tenant) -> Self {
+        let leases = tenant
+            .events()
+            .filter_map(|event| match event {
+                Event::Lease(lease) => Some(lease.clone()),
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            tree: SpatialTree::new(tenant.spaces().cloned().collect()),
+            collections: tenant.collections().cloned().collect(),
+            leases,
+        }
+    }
+
+    pub fn leases(&self) -> &[Lease] {
+        &self.leases
+    }
+
+    /// Leases active `at` covering the space, through the space
+    /// itself, a space it is within or a collection including either
+    pub fn leases_of(&self, space: &Uuid, at: DateTime<Utc>) -> Vec<&Lease> {
+        let mut within: HashSet<Uuid> = self
+            .tree
+            .ancestors(space)
+            .iter()
+            .map(|space| space.id())
+            .collect();
+        within.insert(*space);
+
+        let covers = |id: &Uuid| {
+            within.contains(id)
+                || self.collections.iter().any(|collection| {
+                    collection.id() == *id
+                        && collection
+                            .includes()
+                            .iter()
+                            .any(|relation| within.contains(&relation.id))
+                })
+        };
+
+        self.leases
+            .iter()
+            .filter(|lease| lease.is_active_at(at))
+            .filter(|lease| {
+                lease
+                    .lease_of
+                    .iter()
+                    .flatten()
+                    .any(|relation| covers(&relation.id))
+            })
+            .collect()
+    }
+
+    /// Leases ending within `horizon` after `as_of`, soonest first,
+    /// a horizon past the last representable time has no limit
+    pub fn expiring(&self, as_of: DateTime<Utc>, horizon: Days) -> Vec<LeaseExpiry> {
+        let until = as_of
+            .checked_add_days(horizon)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+
+        let mut expiring: Vec<LeaseExpiry> = self
+            .leases
+            .iter()
+            .filter_map(|lease| {
+                let end = lease.end.filter(|end| as_of <= *end && *end < until)?;
+
+                Some(LeaseExpiry {
+                    lease: Relation::to(&Event::Lease(lease.clone())),
+                    end,
+                    days_left: (end - as_of).num_days(),
+                    leasee: lease.leasee.clone().unwrap_or_default(),
+                    lease_of: lease.lease_of.clone().unwrap_or_default(),
+                })
+            })
+            .collect();
+
+        expiring.sort_by(|a, b| a.end.cmp(&b.end).then(a.lease.name.cmp(&b.lease.name)));
+
+        expiring
+    }
+
+    /// Rooms, premises and apartments without an active lease `on`
+    ///
+    /// Premises and apartments are vacant when neither they nor
+    /// any space they include is leased.
+    pub fn vacancies(&self, on: DateTime<Utc>) -> Vec<Vacancy> {
+        let (leased_spaces, leased_collections) = self.leased(on);
+
+        let mut rooms: Vec<&Space> = self
+            .tree
+            .spaces()
+            .filter(|space| space.space_type() == SpaceType::Room)
+            .filter(|space| !self.is_covered(&space.id(), &leased_spaces))
+            .collect();
+        rooms.sort_by(|a, b| a.name().cmp(b.name()).then(a.id().cmp(&b.id())));
+
+        let mut vacancies: Vec<Vacancy> = rooms
+            .into_iter()
+            .map(|room| Vacancy {
+                unit: Relation::to(room),
+                building: self.tree.building_of(&room.id()).map(Relation::to),
+                rentable_area: self.rentable_area(&room.id()),
+            })
+            .collect();
+
+        let mut collections: Vec<&Collection> = self
+            .collections
+            .iter()
+            .filter(|collection| {
+                matches!(
+                    collection.collection_type(),
+                    CollectionType::Premises | CollectionType::Apartment
+                )
+            })
+            .filter(|collection| !leased_collections.contains(&collection.id()))
+            .filter(|collection| {
+                !collection
+                    .includes()
+                    .iter()
+                    .any(|relation| self.is_covered(&relation.id, &leased_spaces))
+            })
+            .collect();
+        collections.sort_by(|a, b| a.name().cmp(b.name()).then(a.id().cmp(&b.id())));
+
+        vacancies.extend(collections.into_iter().map(|collection| {
+            Vacancy {
+                unit: Relation::to(collection),
+                building: collection
+                    .includes()
+                    .iter()
+                    .find_map(|relation| self.tree.building_of(&relation.id))
+                    .map(Relation::to),
+                rentable_area: self.included_area(collection),
+            }
+        }));
+
+        vacancies
+    }
+
+    /// Leased and rentable area of every building `on`
+    ///
+    /// Leased spaces within another leased space are counted once.
+    pub fn occupancy(&self, on: DateTime<Utc>) -> OccupancyTable {
+        let (leased_spaces, _) = self.leased(on);
+
+        let mut buildings: Vec<&Space> = self
+            .tree
+            .spaces()
+            .filter(|space| space.space_type() == SpaceType::Building)
+            .collect();
+        buildings.sort_by(|a, b| a.name().cmp(b.name()).then(a.id().cmp(&b.id())));
+
+        let rows = buildings
+            .into_iter()
+            .map(|building| {
+                let id = building.id();
+                let rentable_area = self.rentable_area(&id);
+
+                let leased_area = if leased_spaces.contains(&id) {
+                    rentable_area.unwrap_or(0.)
+                } else {
+                    self.tree
+                        .descendants(&id)
+                        .into_iter()
+                        .filter(|space| leased_spaces.contains(&space.id()))
+                        .filter(|space| {
+                            !self
+                                .tree
+                                .ancestors(&space.id())
+                                .iter()
+                                .any(|ancestor| leased_spaces.contains(&ancestor.id()))
+                        })
+                        .filter_map(|space| self.rentable_area(&space.id()))
+                        .sum()
+                };
+
+                BuildingOccupancy {
+                    building: Relation::to(building),
+                    rentable_area,
+                    leased_area,
+                    occupancy: rentable_area
+                        .filter(|area| *area > 0.)
+                        .map(|area| leased_area / area),
+                }
+            })
+            .collect();
+
+        OccupancyTable { on, rows }
+    }
+
+    /// Spaces and collections leased `on`, spaces
+    /// including those of leased collections
+    fn leased(&self, on: DateTime<Utc>) -> (HashSet<Uuid>, HashSet<Uuid>) {
+        let mut spaces = HashSet::new();
+        let mut collections = HashSet::new();
+
+        let leased = self
+            .leases
+            .iter()
+            .filter(|lease| lease.is_active_at(on))
+            .flat_map(|lease| lease.lease_of.iter().flatten());
+
+        for relation in leased {
+            match self.collections.iter().find(|c| c.id() == relation.id) {
+                Some(collection) => {
+                    collections.insert(collection.id());
+                    spaces.extend(collection.includes().iter().map(|relation| relation.id));
+                }
+                None => {
+                    spaces.insert(relation.id);
+                }
+            }
+        }
+
+        (spaces, collections)
+    }
+
+    /// Whether the space or a space it is within is leased
+    fn is_covered(&self, id: &Uuid, leased: &HashSet<Uuid>) -> bool {
+        leased.contains(id)
+            || self
+                .tree
+                .ancestors(id)
+                .iter()
+                .any(|space| leased.contains(&space.id()))
+    }
+
+    /// Rentable area of a space, or the sum of its parts if not set
+    fn rentable_area(&self, id: &Uuid) -> Option<f64> {
+        self.rentable_area_within(id, &mut HashSet::new())
+    }
+
+    fn rentable_area_within(&self, id: &Uuid, visited: &mut HashSet<Uuid>) -> Option<f64> {
+        let space = self.tree.get(id)?;
+
+        if !visited.insert(*id) {
+            return None;
+        }
+
+        if let Some(area) = space.area().and_then(|area| area.rentable_area) {
+            return Some(area);
+        }
+
+        self.tree
+            .children(id)
+            .iter()
+            .filter_map(|child| self.rentable_area_within(&child.id(), visited))
+            .fold(None, |sum, area| Some(sum.unwrap_or(0.) + area))
+    }
+
+    /// Rentable area of the spaces included in a collection
+    fn included_area(&self, collection: &Collection) -> Option<f64> {
+        collection
+            .includes()
+            .iter()
+            .filter_map(|relation| self.rentable_area(&relation.id))
+            .fold(None, |sum, area| Some(sum.unwrap_or(0.) + area))
+    }
+}
+
+impl OccupancyTable {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = export::csv_row(&[
+            "building_id",
+            "building_name",
+            "rentable_area",
+            "leased_area",
+            "occupancy",
+        ]);
+
+        for row in &self.rows {
+            csv.push_str(&export::csv_row(&[
+                row.building.id.to_string(),
+                row.building.name.clone().unwrap_or_default(),
+                row.rentable_area
+                    .map(|area| area.to_string())
+                    .unwrap_or_default(),
+                row.leased_area.to_string(),
+                row.occupancy
+                    .map(|occupancy| occupancy.to_string())
+                    .unwrap_or_default(),
+            ]));
+        }
+
+        csv
+    }
+}
+
+/// Two leases running at the same time for the same space
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaseOverlap {
+    pub first: Relation,
+    pub second: Relation,
+    pub from: DateTime<Utc>,
+    /// End of the overlap, `None` if both leases are open-ended
+    pub until: Option<DateTime<Utc>>,
+    /// Spaces and collections covered by both leases
+    pub targets: Vec<Relation>,
+}
+
+/// Every pair of leases covering the same space at the same time
+///
+/// A lease covers what it is a lease of, every space within
+/// it and, for collections, the spaces the collection includes.
+/// Leases without an end run indefinitely.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct LeaseOverlapReport {
+    pub overlaps: Vec<LeaseOverlap>,
+}
+
+impl LeaseOverlapReport {
+    /// Load the tenant and check its leases
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+
+        Ok(Self::check(&tenant))
+    }
+
+    /// Compare every lease in `tenant` with every other
+    pub fn check(tenant: &Tenant) -> Self {
+        let tree = SpatialTree::new(tenant.spaces().cloned().collect());
+
+        let mut leases: Vec<(&Lease, BTreeSet<Uuid>)> = tenant
+            .events()
+            .filter_map(|event| match event {
+                Event::Lease(lease) => Some(lease),
+                _ => None,
+            })
+            .map(|lease| (lease, Self::coverage(tenant, &tree, lease)))
+            .collect();
+        leases.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start).then(a.id.cmp(&b.id)));
+
+        let mut overlaps = Vec::new();
+
+        for (i, (first, first_covers)) in leases.iter().enumerate() {
+            for (second, second_covers) in &leases[i + 1..] {
+                let from = first.start.max(second.start);
+                let until = match (first.end, second.end) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (end, None) | (None, end) => end,
+                };
+
+                if until.is_some_and(|until| until <= from) {
+                    continue;
+                }
+
+                let targets: Vec<Relation> = first_covers
+                    .intersection(second_covers)
+                    .map(|id| tenant.get(id).map_or(Relation::from(*id), Relation::to))
+                    .collect();
+
+                if targets.is_empty() {
+                    continue;
+                }
+
+                overlaps.push(LeaseOverlap {
+                    first: Relation::to(&Event::Lease((*first).clone())),
+                    second: Relation::to(&Event::Lease((*second).clone())),
+                    from,
+                    until,
+                    targets,
+                });
+            }
+        }
+
+        Self { overlaps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overlaps.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.overlaps.len()
+    }
+
+    /// Ids covered by a lease
+    fn coverage(tenant: &Tenant, tree: &SpatialTree, lease: &Lease) -> BTreeSet<Uuid> {
+        let mut covers = BTreeSet::new();
+
+        for relation in lease.lease_of.iter().flatten() {
+            let spaces: Vec<Uuid> = match tenant.collections().find(|c| c.id() == relation.id) {
+                Some(collection) => {
+                    covers.insert(collection.id());
+                    collection
+                        .includes()
+                        .iter()
+                        .map(|relation| relation.id)
+                        .collect()
+                }
+                None => vec![relation.id],
+            };
+
+            for id in spaces {
+                covers.insert(id);
+                covers.extend(tree.descendants(&id).iter().map(|space| space.id()));
+            }
+        }
+
+        covers
+    }
+}
+
+impl fmt::Display for LeaseOverlapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |relation: &Relation| relation.name.clone().unwrap_or(relation.id.to_string());
+
+        for overlap in &self.overlaps {
+            write!(
+                f,
+                "{} and {} from {}",
+                name(&overlap.first),
+                name(&overlap.second),
+                overlap.from.to_rfc3339()
+            )?;
+
+            match overlap.until {
+                Some(until) => writeln!(f, " until {}", until.to_rfc3339())?,
+                None => writeln!(f)?,
+            }
+
+            for target in &overlap.targets {
+                writeln!(f, "  {}", name(target))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::{collection::Collection, space::Space, Entity};
+
+    fn on(month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn lease(id: u128, start: DateTime<Utc>, end: Option<DateTime<Utc>>, of: &[u128]) -> Entity {
+        let lease_of: Vec<_> = of
+            .iter()
+            .map(|id| json!({"id": Uuid::from_u128(*id)}))
+            .collect();
+
+        serde_json::from_value::<Event>(json!({
+            "type": "lease",
+            "id": Uuid::from_u128(id),
+            "name": format!("Lease {id}"),
+            "start": start,
+            "end": end,
+            "leaseOf": lease_of,
+        }))
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn expiring_within_horizon() {
+        let tenant = Tenant::new(vec![
+            lease(1, on(1, 1), Some(on(3, 1)), &[]),
+            lease(2, on(1, 1), Some(on(2, 1)), &[]),
+            lease(3, on(1, 1), Some(on(12, 1)), &[]),
+            lease(4, on(1, 1), None, &[]),
+        ]);
+        let analytics = LeaseAnalytics::new(&tenant);
+
+        let expiring = analytics.expiring(on(1, 15), Days::new(60));
+        let ids: Vec<_> = expiring.iter().map(|expiry| expiry.lease.id).collect();
+        assert_eq!(ids, [Uuid::from_u128(2), Uuid::from_u128(1)]);
+        assert_eq!(expiring[0].days_left, 17);
+
+        assert_eq!(analytics.expiring(on(1, 15), Days::new(u64::MAX)).len(), 3);
+    }
+
+    #[test]
+    fn vacancies_and_occupancy_follow_the_leases() {
+        let room = |id: u128, area: f64| -> Entity {
+            serde_json::from_value::<Space>(json!({
+                "type": "room",
+                "id": Uuid::from_u128(id),
+                "name": format!("Room {id}"),
+                "isPartOf": [{"id": Uuid::from_u128(1)}],
+                "area": {"id": Uuid::from_u128(id + 10), "type": "area", "name": "Area", "rentableArea": area},
+            }))
+            .unwrap()
+            .into()
+        };
+
+        let tenant = Tenant::new(vec![
+            serde_json::from_value::<Space>(json!({
+                "type": "building",
+                "id": Uuid::from_u128(1),
+                "name": "Main",
+            }))
+            .unwrap()
+            .into(),
+            room(3, 100.),
+            room(4, 50.),
+            room(5, 50.),
+            serde_json::from_value::<Collection>(json!({
+                "type": "premises",
+                "id": Uuid::from_u128(6),
+                "name": "Premises",
+                "includes": [{"id": Uuid::from_u128(4)}],
+            }))
+            .unwrap()
+            .into(),
+            lease(7, on(1, 1), Some(on(6, 1)), &[3]),
+            lease(8, on(1, 1), None, &[6]),
+            lease(9, on(7, 1), None, &[5]),
+        ]);
+        let analytics = LeaseAnalytics::new(&tenant);
+
+        let vacant = |on| -> Vec<Uuid> {
+            analytics
+                .vacancies(on)
+                .iter()
+                .map(|vacancy| vacancy.unit.id)
+                .collect()
+        };
+        assert_eq!(vacant(on(3, 1)), [Uuid::from_u128(5)]);
+        assert_eq!(vacant(on(6, 1)), [Uuid::from_u128(3), Uuid::from_u128(5)]);
+
+        let occupancy = analytics.occupancy(on(3, 1));
+        assert_eq!(occupancy.rows[0].rentable_area, Some(200.));
+        assert_eq!(occupancy.rows[0].leased_area, 150.);
+    }
+
+    fn space(id: u128, space_type: &str, part_of: &[u128]) -> Entity {
+        let is_part_of: Vec<_> = part_of
+            .iter()
+            .map(|id| json!({"id": Uuid::from_u128(*id)}))
+            .collect();
+
+        serde_json::from_value::<Space>(json!({
+            "type": space_type,
+            "id": Uuid::from_u128(id),
+            "name": format!("Space {id}"),
+            "isPartOf": is_part_of,
+        }))
+        .unwrap()
+        .into()
+    }
+
+    fn premises(id: u128, includes: &[u128]) -> Entity {
+        let includes: Vec<_> = includes
+            .iter()
+            .map(|id| json!({"id": Uuid::from_u128(*id)}))
+            .collect();
+
+        serde_json::from_value::<Collection>(json!({
+            "type": "premises",
+            "id": Uuid::from_u128(id),
+            "name": format!("Premises {id}"),
+            "includes": includes,
+        }))
+        .unwrap()
+        .into()
+    }
+
+    fn pairs(report: &LeaseOverlapReport) -> Vec<(Uuid, Uuid)> {
+        report
+            .overlaps
+            .iter()
+            .map(|overlap| (overlap.first.id, overlap.second.id))
+            .collect()
+    }
+
+    #[test]
+    fn overlap_through_a_collection() {
+        let report = LeaseOverlapReport::check(&Tenant::new(vec![
+            space(3, "room", &[]),
+            space(4, "room", &[]),
+            premises(6, &[4]),
+            lease(10, on(1, 1), None, &[6]),
+            lease(11, on(3, 1), Some(on(5, 1)), &[4]),
+            lease(12, on(1, 1), None, &[3]),
+        ]));
+
+        assert_eq!(pairs(&report), [(Uuid::from_u128(10), Uuid::from_u128(11))]);
+        assert_eq!(report.overlaps[0].from, on(3, 1));
+        assert_eq!(report.overlaps[0].until, Some(on(5, 1)));
+
+        let targets: Vec<_> = report.overlaps[0]
+            .targets
+            .iter()
+            .map(|target| target.id)
+            .collect();
+        assert_eq!(targets, [Uuid::from_u128(4)]);
+    }
+
+    #[test]
+    fn overlap_with_a_space_within_the_lease() {
+        let report = LeaseOverlapReport::check(&Tenant::new(vec![
+            space(1, "building", &[]),
+            space(2, "level", &[1]),
+            space(3, "room", &[2]),
+            lease(10, on(1, 1), None, &[2]),
+            lease(11, on(2, 1), None, &[3]),
+        ]));
+
+        assert_eq!(pairs(&report), [(Uuid::from_u128(10), Uuid::from_u128(11))]);
+        assert_eq!(report.overlaps[0].until, None);
+    }
+
+    #[test]
+    fn consecutive_leases_do_not_overlap() {
+        let report = LeaseOverlapReport::check(&Tenant::new(vec![
+            space(3, "room", &[]),
+            lease(10, on(1, 1), Some(on(3, 1)), &[3]),
+            lease(11, on(3, 1), None, &[3]),
+        ]));
+
+        assert!(report.is_empty(), "{report}");
+    }
+}
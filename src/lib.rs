@@ -1,3 +1,24 @@
+pub mod access;
+pub mod audit;
+pub mod availability;
+pub mod booking;
+pub mod cascade;
+pub mod consistency;
+pub mod directory;
+pub mod energy;
 pub mod error;
+mod export;
 pub mod handler;
+pub mod ical;
+pub mod integrity;
+pub mod lease;
+pub mod maintenance;
+pub mod metering;
 pub mod models;
+pub mod recurrence;
+pub mod rent;
+pub mod spatial;
+pub mod stale;
+pub mod tenant;
+pub mod utilisation;
+pub mod visitor;
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    export,
+    handler::Handler,
+    models::{asset::Asset, Relation},
+    spatial::SpatialTree,
+    tenant::Tenant,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceStatus {
+    Overdue,
+    Upcoming,
+}
+
+impl ToString for MaintenanceStatus {
+    fn to_string(&self) -> String {
+        match self {
+            MaintenanceStatus::Overdue => "overdue".into(),
+            MaintenanceStatus::Upcoming => "upcoming".into(),
+        }
+    }
+}
+
+/// Service of an asset due on `due`
+#[derive(Serialize, Debug, Clone)]
+pub struct MaintenanceTask {
+    pub asset: Relation,
+    pub building: Option<Relation>,
+    pub due: NaiveDate,
+    pub status: MaintenanceStatus,
+}
+
+/// Overdue and upcoming services of doors and meters
+///
+/// `maintenance_interval` is taken as a number of days. The first
+/// service is due one interval after the last service, or after
+/// `installation_date` for assets never serviced. A missed service
+/// is reported once as overdue, followed by the services due
+/// within the horizon.
+#[derive(Serialize, Debug, Clone)]
+pub struct MaintenanceSchedule {
+    pub as_of: NaiveDate,
+    pub until: NaiveDate,
+    /// Tasks ordered by building, then due date
+    pub tasks: Vec<MaintenanceTask>,
+}
+
+impl MaintenanceSchedule {
+    /// Load the tenant and plan services up to `horizon` after `as_of`
+    pub async fn load(
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        last_serviced: &HashMap<Uuid, NaiveDate>,
+        as_of: NaiveDate,
+        horizon: Days,
+    ) -> Result<Self, Error> {
+        let tenant = Tenant::load(handler, client, token).await?;
+
+        Ok(Self::new(&tenant, last_serviced, as_of, horizon))
+    }
+
+    /// Plan services up to `horizon` after `as_of`
+    ///
+    /// Dates past the end of the calendar are left out.
+    pub fn new(
+        tenant: &Tenant,
+        last_serviced: &HashMap<Uuid, NaiveDate>,
+        as_of: NaiveDate,
+        horizon: Days,
+    ) -> Self {
+        let tree = SpatialTree::new(tenant.spaces().cloned().collect());
+        let until = as_of.checked_add_days(horizon).unwrap_or(NaiveDate::MAX);
+        let mut tasks = Vec::new();
+
+        for asset in tenant.assets() {
+            let Some(interval) = asset.maintenance_interval().filter(|days| *days > 0) else {
+                continue;
+            };
+            let interval = Days::new(interval.into());
+
+            let base = last_serviced
+                .get(&asset.id())
+                .copied()
+                .or_else(|| asset.installation_date().and_then(parse_date));
+
+            let Some(base) = base else {
+                continue;
+            };
+
+            let building = Self::building(&tree, asset);
+            let mut next = base.checked_add_days(interval);
+
+            if let Some(due) = next.filter(|due| *due < as_of) {
+                tasks.push(MaintenanceTask {
+                    asset: Relation::to(asset),
+                    building: building.clone(),
+                    due,
+                    status: MaintenanceStatus::Overdue,
+                });
+
+                while let Some(due) = next.filter(|due| *due < as_of) {
+                    next = due.checked_add_days(interval);
+                }
+            }
+
+            while let Some(due) = next.filter(|due| *due <= until) {
+                tasks.push(MaintenanceTask {
+                    asset: Relation::to(asset),
+                    building: building.clone(),
+                    due,
+                    status: MaintenanceStatus::Upcoming,
+                });
+
+                next = due.checked_add_days(interval);
+            }
+        }
+
+        tasks.sort_by(|a, b| {
+            let building =
+                |task: &MaintenanceTask| task.building.as_ref().and_then(|b| b.name.clone());
+
+            building(a)
+                .cmp(&building(b))
+                .then(a.due.cmp(&b.due))
+                .then(a.asset.id.cmp(&b.asset.id))
+        });
+
+        Self {
+            as_of,
+            until,
+            tasks,
+        }
+    }
+
+    /// Building of the first space the asset is located in
+    fn building(tree: &SpatialTree, asset: &Asset) -> Option<Relation> {
+        asset
+            .located_in()
+            .iter()
+            .find_map(|relation| tree.building_of(&relation.id))
+            .map(Relation::to)
+    }
+
+    pub fn overdue(&self) -> impl Iterator<Item = &MaintenanceTask> {
+        self.tasks
+            .iter()
+            .filter(|task| task.status == MaintenanceStatus::Overdue)
+    }
+
+    pub fn upcoming(&self) -> impl Iterator<Item = &MaintenanceTask> {
+        self.tasks
+            .iter()
+            .filter(|task| task.status == MaintenanceStatus::Upcoming)
+    }
+
+    /// Tasks grouped by building, `None` for assets
+    /// not located in any building
+    pub fn by_building(&self) -> Vec<(Option<&Relation>, Vec<&MaintenanceTask>)> {
+        let mut groups: Vec<(Option<&Relation>, Vec<&MaintenanceTask>)> = Vec::new();
+
+        for task in &self.tasks {
+            let building = task.building.as_ref();
+
+            match groups.last_mut() {
+                Some((current, tasks)) if current.map(|b| b.id) == building.map(|b| b.id) => {
+                    tasks.push(task)
+                }
+                _ => groups.push((building, vec![task])),
+            }
+        }
+
+        groups
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = export::csv_row(&[
+            "building_id",
+            "building_name",
+            "asset_id",
+            "asset_type",
+            "asset_name",
+            "due",
+            "status",
+        ]);
+
+        for task in &self.tasks {
+            csv.push_str(&export::csv_row(&[
+                task.building
+                    .as_ref()
+                    .map(|b| b.id.to_string())
+                    .unwrap_or_default(),
+                task.building
+                    .as_ref()
+                    .and_then(|b| b.name.clone())
+                    .unwrap_or_default(),
+                task.asset.id.to_string(),
+                task.asset.rtype.clone().unwrap_or_default(),
+                task.asset.name.clone().unwrap_or_default(),
+                task.due.to_string(),
+                task.status.to_string(),
+            ]));
+        }
+
+        csv
+    }
+
+    /// Calendar with one all-day event per task
+    pub fn to_ical(&self, stamp: DateTime<Utc>) -> String {
+        let mut ical = String::new();
+        let stamp = export::ical_datetime(stamp);
+
+        ical.push_str(&export::ical_line("BEGIN:VCALENDAR"));
+        ical.push_str(&export::ical_line("VERSION:2.0"));
+        ical.push_str(&export::ical_line("PRODID:-//srenity-rs//maintenance//EN"));
+
+        for task in &self.tasks {
+            let name = task.asset.name.as_deref().unwrap_or_default();
+
+            ical.push_str(&export::ical_line("BEGIN:VEVENT"));
+            ical.push_str(&export::ical_line(&format!(
+                "UID:{}-{}@srenity",
+                task.asset.id,
+                task.due.format("%Y%m%d")
+            )));
+            ical.push_str(&export::ical_line(&format!("DTSTAMP:{stamp}")));
+            ical.push_str(&export::ical_line(&format!(
+                "DTSTART;VALUE=DATE:{}",
+                task.due.format("%Y%m%d")
+            )));
+            ical.push_str(&export::ical_line(&format!(
+                "SUMMARY:{}",
+                export::ical_text(&format!("Maintenance: {name}"))
+            )));
+
+            if let Some(building) = task.building.as_ref().and_then(|b| b.name.as_deref()) {
+                ical.push_str(&export::ical_line(&format!(
+                    "LOCATION:{}",
+                    export::ical_text(building)
+                )));
+            }
+
+            ical.push_str(&export::ical_line(&format!(
+                "CATEGORIES:{}",
+                task.status.to_string().to_uppercase()
+            )));
+            ical.push_str(&export::ical_line("END:VEVENT"));
+        }
+
+        ical.push_str(&export::ical_line("END:VCALENDAR"));
+
+        ical
+    }
+}
+
+/// Parse a date from an RFC 3339 timestamp or `YYYY-MM-DD`
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.date_naive())
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::{space::Space, Entity};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn door(interval: u32) -> Entity {
+        serde_json::from_value::<Asset>(json!({
+            "type": "door",
+            "id": Uuid::from_u128(2),
+            "name": "Front",
+            "installationDate": "2024-01-01",
+            "maintenanceInterval": interval,
+            "locatedIn": [{"id": Uuid::from_u128(1)}],
+        }))
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn missed_services_are_reported_once() {
+        let last_serviced = HashMap::from([(Uuid::from_u128(2), date(5, 20))]);
+        let tenant = Tenant::new(vec![
+            serde_json::from_value::<Space>(json!({
+                "type": "building",
+                "id": Uuid::from_u128(1),
+                "name": "HQ, North",
+            }))
+            .unwrap()
+            .into(),
+            door(30),
+            serde_json::from_value::<Asset>(json!({
+                "type": "meter",
+                "id": Uuid::from_u128(3),
+                "name": "Main meter",
+                "installationDate": "2024-06-01T00:00:00Z",
+                "maintenanceInterval": 100,
+            }))
+            .unwrap()
+            .into(),
+        ]);
+        let schedule = MaintenanceSchedule::new(&tenant, &last_serviced, date(7, 1), Days::new(60));
+
+        let tasks: Vec<_> = schedule
+            .tasks
+            .iter()
+            .map(|task| (task.asset.id.as_u128(), task.due, task.status))
+            .collect();
+        assert_eq!(
+            tasks,
+            [
+                (2, date(6, 19), MaintenanceStatus::Overdue),
+                (2, date(7, 19), MaintenanceStatus::Upcoming),
+                (2, date(8, 18), MaintenanceStatus::Upcoming),
+            ]
+        );
+        assert_eq!(schedule.overdue().count(), 1);
+
+        let groups = schedule.by_building();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0].0.map(|building| building.id),
+            Some(Uuid::from_u128(1))
+        );
+
+        let ical = schedule.to_ical(Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap());
+        assert!(ical.contains("LOCATION:HQ\\, North\r\n"));
+        assert!(ical.contains("DTSTART;VALUE=DATE:20240619\r\n"));
+    }
+
+    #[test]
+    fn dates_past_the_calendar_are_left_out() {
+        let schedule = MaintenanceSchedule::new(
+            &Tenant::new(vec![door(4_000_000_000)]),
+            &HashMap::new(),
+            date(7, 1),
+            Days::new(u64::MAX),
+        );
+
+        assert!(schedule.tasks.is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::asset::{Asset, AssetType},
+    spatial::SpatialTree,
+};
+
+/// Meters of a building split by their place in the feeds graph
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct BuildingMeters {
+    /// Physical meters not fed by another physical meter
+    pub main: Vec<Uuid>,
+    /// Physical meters fed by another physical meter
+    pub sub: Vec<Uuid>,
+    pub virtual_meters: Vec<Uuid>,
+}
+
+/// Graph of meters linked by `feeds`, pointing from
+/// the supplying meter to the supplied one
+///
+/// A virtual meter has no reading of its own. Its value is the
+/// sum of the meters feeding it, virtual feeders being resolved
+/// down to the physical meters behind them. A physical meter fed
+/// by another of those meters is already measured by it, and is
+/// not counted again.
+#[derive(Debug, Clone, Default)]
+pub struct MeterTopology {
+    meters: HashMap<Uuid, Asset>,
+    downstream: HashMap<Uuid, Vec<Uuid>>,
+    upstream: HashMap<Uuid, Vec<Uuid>>,
+}
+
+impl MeterTopology {
+    /// Load meters of every type and link them
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let mut meters = Vec::new();
+
+        for asset_type in AssetType::ALL.into_iter().filter(AssetType::is_meter) {
+            meters.extend(handler.assets(client, token, asset_type).await?);
+        }
+
+        Ok(Self::new(meters))
+    }
+
+    /// Link already fetched meters, other assets are ignored
+    pub fn new(assets: Vec<Asset>) -> Self {
+        let meters: HashMap<Uuid, Asset> = assets
+            .into_iter()
+            .filter(Asset::is_meter)
+            .map(|asset| (asset.id(), asset))
+            .collect();
+
+        let mut downstream: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let mut upstream: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+
+        for (id, meter) in &meters {
+            let fed: BTreeSet<Uuid> = meter
+                .feeds()
+                .iter()
+                .map(|relation| relation.id)
+                .filter(|fed| meters.contains_key(fed))
+                .collect();
+
+            for fed in fed {
+                downstream.entry(*id).or_default().push(fed);
+                upstream.entry(fed).or_default().push(*id);
+            }
+        }
+
+        for ids in upstream.values_mut() {
+            ids.sort();
+        }
+
+        Self {
+            meters,
+            downstream,
+            upstream,
+        }
+    }
+
+    /// Get meter by id
+    pub fn get(&self, id: &Uuid) -> Option<&Asset> {
+        self.meters.get(id)
+    }
+
+    /// Meters fed by `id`
+    pub fn feeds(&self, id: &Uuid) -> &[Uuid] {
+        self.downstream
+            .get(id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Meters feeding `id`
+    pub fn fed_by(&self, id: &Uuid) -> &[Uuid] {
+        self.upstream.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Every meter upstream of `id`, directly or through other meters
+    pub fn suppliers(&self, id: &Uuid) -> Vec<Uuid> {
+        let mut visited = HashSet::from([*id]);
+        let mut stack: Vec<Uuid> = self.fed_by(id).to_vec();
+        let mut suppliers = Vec::new();
+
+        while let Some(next) = stack.pop() {
+            if visited.insert(next) {
+                suppliers.push(next);
+                stack.extend(self.fed_by(&next));
+            }
+        }
+
+        suppliers
+    }
+
+    /// Groups of meters feeding each other in a loop
+    pub fn cycles(&self) -> Vec<Vec<Uuid>> {
+        let mut ids: Vec<Uuid> = self.meters.keys().copied().collect();
+        ids.sort();
+
+        let mut tarjan = Tarjan::default();
+
+        for id in ids {
+            if !tarjan.index.contains_key(&id) {
+                tarjan.connect(self, id);
+            }
+        }
+
+        tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.feeds(&component[0]).contains(&component[0])
+            })
+            .collect()
+    }
+
+    /// Meters located in `building` or any space within it
+    pub fn building_meters(&self, tree: &SpatialTree, building: &Uuid) -> BuildingMeters {
+        let mut res = BuildingMeters::default();
+
+        let mut ids: Vec<&Uuid> = self
+            .meters
+            .iter()
+            .filter(|(_, meter)| {
+                meter.located_in().iter().any(|relation| {
+                    tree.building_of(&relation.id)
+                        .is_some_and(|space| space.id() == *building)
+                })
+            })
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+
+        for id in ids {
+            let meter = &self.meters[id];
+
+            if meter.is_virtual_meter() {
+                res.virtual_meters.push(*id);
+            } else if self.fed_by(id).iter().any(|feeder| {
+                self.meters
+                    .get(feeder)
+                    .is_some_and(|m| !m.is_virtual_meter())
+            }) {
+                res.sub.push(*id);
+            } else {
+                res.main.push(*id);
+            }
+        }
+
+        res
+    }
+
+    /// Physical meters a virtual meter is derived from,
+    /// the meter itself if it is physical
+    ///
+    /// Meters downstream of another source are left out,
+    /// their consumption being part of that source's.
+    pub fn sources(&self, id: &Uuid) -> Vec<Uuid> {
+        let Some(meter) = self.meters.get(id) else {
+            return Vec::new();
+        };
+
+        if !meter.is_virtual_meter() {
+            return vec![*id];
+        }
+
+        let mut visited = HashSet::from([*id]);
+        let mut stack: Vec<Uuid> = self.fed_by(id).to_vec();
+        let mut sources = BTreeSet::new();
+
+        while let Some(next) = stack.pop() {
+            if !visited.insert(next) {
+                continue;
+            }
+
+            match self.meters.get(&next) {
+                Some(meter) if meter.is_virtual_meter() => {
+                    stack.extend(self.fed_by(&next));
+                }
+                Some(_) => {
+                    sources.insert(next);
+                }
+                None => {}
+            }
+        }
+
+        sources
+            .iter()
+            .filter(|source| {
+                !self
+                    .suppliers(source)
+                    .iter()
+                    .any(|supplier| sources.contains(supplier))
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Value of a meter given the values of physical meters
+    ///
+    /// Returns `None` if a source has no value, or if a
+    /// virtual meter has no sources.
+    pub fn value(&self, id: &Uuid, values: &HashMap<Uuid, f64>) -> Option<f64> {
+        let sources = self.sources(id);
+
+        if sources.is_empty() {
+            return None;
+        }
+
+        sources
+            .iter()
+            .map(|source| values.get(source).copied())
+            .sum()
+    }
+}
+
+/// Tarjan's strongly connected components over `feeds`
+#[derive(Default)]
+struct Tarjan {
+    next: usize,
+    index: HashMap<Uuid, usize>,
+    low: HashMap<Uuid, usize>,
+    stack: Vec<Uuid>,
+    on_stack: HashSet<Uuid>,
+    components: Vec<Vec<Uuid>>,
+}
+
+impl Tarjan {
+    /// Visit every meter reachable from `id`, keeping its own
+    /// call stack so long chains of meters can not overflow
+    fn connect(&mut self, topology: &MeterTopology, id: Uuid) {
+        let mut calls: Vec<(Uuid, usize)> = vec![(id, 0)];
+        self.visit(id);
+
+        while let Some((id, edge)) = calls.last_mut() {
+            let id = *id;
+
+            if let Some(fed) = topology.feeds(&id).get(*edge) {
+                *edge += 1;
+
+                if !self.index.contains_key(fed) {
+                    self.visit(*fed);
+                    calls.push((*fed, 0));
+                } else if self.on_stack.contains(fed) {
+                    let low = self.low[&id].min(self.index[fed]);
+                    self.low.insert(id, low);
+                }
+
+                continue;
+            }
+
+            calls.pop();
+
+            if let Some((caller, _)) = calls.last() {
+                let low = self.low[caller].min(self.low[&id]);
+                self.low.insert(*caller, low);
+            }
+
+            if self.low[&id] == self.index[&id] {
+                let mut component = Vec::new();
+
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(&member);
+                    component.push(member);
+
+                    if member == id {
+                        break;
+                    }
+                }
+
+                component.sort();
+                self.components.push(component);
+            }
+        }
+    }
+
+    fn visit(&mut self, id: Uuid) {
+        self.index.insert(id, self.next);
+        self.low.insert(id, self.next);
+        self.next += 1;
+        self.stack.push(id);
+        self.on_stack.insert(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn id(id: u128) -> Uuid {
+        Uuid::from_u128(id)
+    }
+
+    fn meter(meter: u128, feeds: &[u128], is_virtual: bool) -> Asset {
+        let feeds: Vec<_> = feeds.iter().map(|fed| json!({"id": id(*fed)})).collect();
+
+        serde_json::from_value(json!({
+            "type": "electrical_meter",
+            "id": id(meter),
+            "name": format!("Meter {meter}"),
+            "feeds": feeds,
+            "isVirtualMeter": is_virtual,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cycles_are_found() {
+        let topology = MeterTopology::new(vec![
+            meter(1, &[2], false),
+            meter(2, &[3], false),
+            meter(3, &[1], false),
+            meter(4, &[4], false),
+            meter(5, &[1], false),
+        ]);
+
+        assert_eq!(topology.cycles(), [vec![id(1), id(2), id(3)], vec![id(4)]]);
+    }
+
+    #[test]
+    fn long_chains_do_not_overflow() {
+        let chain: Vec<Asset> = (0..100_000)
+            .map(|n| meter(n, &[n + 1], false))
+            .chain([meter(100_000, &[0], false)])
+            .collect();
+
+        let cycles = MeterTopology::new(chain).cycles();
+
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].len(), 100_001);
+    }
+
+    #[test]
+    fn virtual_meter_sums_physical_sources() {
+        let topology = MeterTopology::new(vec![
+            meter(2, &[9], false),
+            meter(3, &[8], false),
+            meter(8, &[9], true),
+            meter(9, &[], true),
+        ]);
+
+        assert_eq!(topology.sources(&id(9)), [id(2), id(3)]);
+
+        let values = HashMap::from([(id(2), 1.5), (id(3), 2.)]);
+        assert_eq!(topology.value(&id(9), &values), Some(3.5));
+        assert_eq!(topology.value(&id(9), &HashMap::new()), None);
+    }
+
+    #[test]
+    fn sub_meter_of_another_source_is_counted_once() {
+        let topology = MeterTopology::new(vec![
+            meter(1, &[2, 9], false),
+            meter(2, &[9], false),
+            meter(9, &[], true),
+        ]);
+
+        assert_eq!(topology.sources(&id(9)), [id(1)]);
+
+        let values = HashMap::from([(id(1), 10.), (id(2), 4.)]);
+        assert_eq!(topology.value(&id(9), &values), Some(10.));
+    }
+}
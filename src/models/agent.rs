@@ -1,7 +1,8 @@
-use super::{Id, Identifier, Relation};
+use super::{Id, Identifier, Relation, RelationField, Resource};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use serde_with::skip_serializing_none;
-use std::{collections::HashMap, ops::Deref};
+use serde_with::{skip_serializing_none, DeserializeFromStr, SerializeDisplay};
+use std::{collections::HashMap, convert::Infallible, fmt, ops::Deref, str::FromStr};
 use uuid::Uuid;
 use wrapi::{http::Method, request::Request};
 
@@ -16,7 +17,7 @@ impl Deref for AgentId {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentType {
     AccessGroup,
@@ -25,6 +26,16 @@ pub enum AgentType {
     Person,
 }
 
+impl AgentType {
+    /// Every agent type known to the API
+    pub const ALL: [AgentType; 4] = [
+        AgentType::AccessGroup,
+        AgentType::Company,
+        AgentType::Department,
+        AgentType::Person,
+    ];
+}
+
 impl ToString for AgentType {
     fn to_string(&self) -> String {
         match self {
@@ -45,6 +56,112 @@ pub enum Agent {
     Person(Person),
 }
 
+impl Agent {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Agent::AccessGroup(agent) => agent.id,
+            Agent::Company(agent) => agent.id,
+            Agent::Department(agent) => agent.id,
+            Agent::Person(agent) => agent.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Agent::AccessGroup(agent) => &agent.name,
+            Agent::Company(agent) => &agent.name,
+            Agent::Department(agent) => &agent.name,
+            Agent::Person(agent) => &agent.name,
+        }
+    }
+
+    pub fn agent_type(&self) -> AgentType {
+        match self {
+            Agent::AccessGroup(_) => AgentType::AccessGroup,
+            Agent::Company(_) => AgentType::Company,
+            Agent::Department(_) => AgentType::Department,
+            Agent::Person(_) => AgentType::Person,
+        }
+    }
+}
+
+impl Resource for Agent {
+    fn id(&self) -> Uuid {
+        Agent::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Agent::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.agent_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        match self {
+            Agent::AccessGroup(agent) => vec![
+                ("member_of", agent.member_of.as_deref().unwrap_or_default()),
+                (
+                    "has_member",
+                    agent.has_member.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "includes_door",
+                    agent.includes_door.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "includes_zone",
+                    agent.includes_zone.as_deref().unwrap_or_default(),
+                ),
+            ],
+            Agent::Company(agent) => vec![
+                ("member_of", agent.member_of.as_deref().unwrap_or_default()),
+                (
+                    "has_member",
+                    agent.has_member.as_deref().unwrap_or_default(),
+                ),
+            ],
+            Agent::Department(agent) => vec![
+                ("member_of", agent.member_of.as_deref().unwrap_or_default()),
+                (
+                    "has_member",
+                    agent.has_member.as_deref().unwrap_or_default(),
+                ),
+            ],
+            Agent::Person(agent) => {
+                vec![("member_of", agent.member_of.as_deref().unwrap_or_default())]
+            }
+        }
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        match self {
+            Agent::AccessGroup(agent) => vec![
+                ("member_of", RelationField::Many(&mut agent.member_of)),
+                ("has_member", RelationField::Many(&mut agent.has_member)),
+                (
+                    "includes_door",
+                    RelationField::Many(&mut agent.includes_door),
+                ),
+                (
+                    "includes_zone",
+                    RelationField::Many(&mut agent.includes_zone),
+                ),
+            ],
+            Agent::Company(agent) => vec![
+                ("member_of", RelationField::Many(&mut agent.member_of)),
+                ("has_member", RelationField::Many(&mut agent.has_member)),
+            ],
+            Agent::Department(agent) => vec![
+                ("member_of", RelationField::Many(&mut agent.member_of)),
+                ("has_member", RelationField::Many(&mut agent.has_member)),
+            ],
+            Agent::Person(agent) => vec![("member_of", RelationField::Many(&mut agent.member_of))],
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -139,11 +256,79 @@ pub enum NewAgent {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Type of a person key, known types are read in any casing
+#[derive(SerializeDisplay, DeserializeFromStr, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyType {
+    Card,
+    Fob,
+    Pin,
+    Mobile,
+    /// Type not known to this crate, kept as sent
+    Other(String),
+}
+
+impl FromStr for KeyType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "card" => KeyType::Card,
+            "fob" => KeyType::Fob,
+            "pin" => KeyType::Pin,
+            "mobile" => KeyType::Mobile,
+            _ => KeyType::Other(s.into()),
+        })
+    }
+}
+
+impl fmt::Display for KeyType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyType::Card => write!(f, "card"),
+            KeyType::Fob => write!(f, "fob"),
+            KeyType::Pin => write!(f, "pin"),
+            KeyType::Mobile => write!(f, "mobile"),
+            KeyType::Other(key_type) => write!(f, "{key_type}"),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Key {
     #[serde(rename = "type")]
-    pub key_type: String,
+    pub key_type: KeyType,
+    pub provider: String,
+    pub key: String,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl Key {
+    /// Whether the key is within its validity window at `at`
+    // `Option::is_none_or` needs Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from.map_or(true, |from| from <= at)
+            && self.valid_until.map_or(true, |until| at < until)
+    }
+}
+
+/// Key to issue to a person
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewKey {
+    #[serde(skip)]
+    pub person: Uuid,
+    #[serde(flatten)]
+    pub key: Key,
+}
+
+/// Key of a person to revoke, identified by provider and key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevokeKey {
+    #[serde(skip)]
+    pub person: Uuid,
     pub provider: String,
     pub key: String,
 }
@@ -240,3 +425,71 @@ impl Request<Vec<Key>> for Id {
         None
     }
 }
+
+/// Issue key to person
+impl Request<()> for NewKey {
+    fn endpoint(&self) -> String {
+        format!("person/{}/keys", self.person)
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+}
+
+/// Revoke key of person
+impl Request<()> for RevokeKey {
+    fn endpoint(&self) -> String {
+        format!("person/{}/keys", self.person)
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn key_types_keep_unknown_values() {
+        let keys: Vec<Key> = serde_json::from_value(json!([
+            {"type": "Card", "provider": "acme", "key": "1"},
+            {"type": "biometric", "provider": "acme", "key": "2"},
+        ]))
+        .unwrap();
+
+        assert_eq!(keys[0].key_type, KeyType::Card);
+        assert_eq!(keys[1].key_type, KeyType::Other("biometric".into()));
+
+        let written = serde_json::to_value(&keys).unwrap();
+        assert_eq!(written[0]["type"], "card");
+        assert_eq!(written[1]["type"], "biometric");
+    }
+
+    #[test]
+    fn key_validity_window() {
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        let key = Key {
+            key_type: KeyType::Pin,
+            provider: "acme".into(),
+            key: "0000".into(),
+            valid_from: Some(at(9)),
+            valid_until: Some(at(11)),
+        };
+
+        assert!(!key.is_valid_at(at(8)));
+        assert!(key.is_valid_at(at(9)));
+        assert!(!key.is_valid_at(at(11)));
+        assert!(Key {
+            valid_from: None,
+            valid_until: None,
+            ..key
+        }
+        .is_valid_at(at(0)));
+    }
+}
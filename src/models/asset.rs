@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use uuid::Uuid;
 use wrapi::{http::Method, request::Request};
 
-use super::{Id, Identifier, Relation};
+use super::{Id, Identifier, Relation, RelationField, Resource};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AssetId(pub Id);
+
+impl Deref for AssetId {
+    type Target = Id;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AssetType {
     Door,
     Meter,
@@ -17,6 +28,22 @@ pub enum AssetType {
     ChilledWaterMeter,
 }
 
+impl AssetType {
+    /// Every asset type known to the API
+    pub const ALL: [AssetType; 6] = [
+        AssetType::Door,
+        AssetType::Meter,
+        AssetType::ElectricalMeter,
+        AssetType::GasMeter,
+        AssetType::HotWaterMeter,
+        AssetType::ChilledWaterMeter,
+    ];
+
+    pub fn is_meter(&self) -> bool {
+        !matches!(self, AssetType::Door)
+    }
+}
+
 impl ToString for AssetType {
     fn to_string(&self) -> String {
         match self {
@@ -41,6 +68,192 @@ pub enum Asset {
     ChilledWaterMeter(ChilledWaterMeter),
 }
 
+impl Asset {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Asset::Door(asset) => asset.id,
+            Asset::Meter(asset) => asset.id,
+            Asset::ElectricalMeter(asset) => asset.id,
+            Asset::GasMeter(asset) => asset.id,
+            Asset::HotWaterMeter(asset) => asset.id,
+            Asset::ChilledWaterMeter(asset) => asset.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Asset::Door(asset) => &asset.name,
+            Asset::Meter(asset) => &asset.name,
+            Asset::ElectricalMeter(asset) => &asset.name,
+            Asset::GasMeter(asset) => &asset.name,
+            Asset::HotWaterMeter(asset) => &asset.name,
+            Asset::ChilledWaterMeter(asset) => &asset.name,
+        }
+    }
+
+    pub fn asset_type(&self) -> AssetType {
+        match self {
+            Asset::Door(_) => AssetType::Door,
+            Asset::Meter(_) => AssetType::Meter,
+            Asset::ElectricalMeter(_) => AssetType::ElectricalMeter,
+            Asset::GasMeter(_) => AssetType::GasMeter,
+            Asset::HotWaterMeter(_) => AssetType::HotWaterMeter,
+            Asset::ChilledWaterMeter(_) => AssetType::ChilledWaterMeter,
+        }
+    }
+
+    /// Spaces the asset is located in
+    pub fn located_in(&self) -> &[Relation] {
+        let relations = match self {
+            Asset::Door(asset) => &asset.located_in,
+            Asset::Meter(asset) => &asset.located_in,
+            Asset::ElectricalMeter(asset) => &asset.located_in,
+            Asset::GasMeter(asset) => &asset.located_in,
+            Asset::HotWaterMeter(asset) => &asset.located_in,
+            Asset::ChilledWaterMeter(asset) => &asset.located_in,
+        };
+
+        relations.as_deref().unwrap_or_default()
+    }
+
+    /// Meters fed by this meter, empty for doors
+    pub fn feeds(&self) -> &[Relation] {
+        let relations = match self {
+            Asset::Door(_) => return &[],
+            Asset::Meter(asset) => &asset.feeds,
+            Asset::ElectricalMeter(asset) => &asset.feeds,
+            Asset::GasMeter(asset) => &asset.feeds,
+            Asset::HotWaterMeter(asset) => &asset.feeds,
+            Asset::ChilledWaterMeter(asset) => &asset.feeds,
+        };
+
+        relations.as_deref().unwrap_or_default()
+    }
+
+    pub fn installation_date(&self) -> Option<&str> {
+        match self {
+            Asset::Door(asset) => asset.installation_date.as_deref(),
+            Asset::Meter(asset) => asset.installation_date.as_deref(),
+            Asset::ElectricalMeter(asset) => asset.installation_date.as_deref(),
+            Asset::GasMeter(asset) => asset.installation_date.as_deref(),
+            Asset::HotWaterMeter(asset) => asset.installation_date.as_deref(),
+            Asset::ChilledWaterMeter(asset) => asset.installation_date.as_deref(),
+        }
+    }
+
+    pub fn maintenance_interval(&self) -> Option<u32> {
+        match self {
+            Asset::Door(asset) => asset.maintenance_interval,
+            Asset::Meter(asset) => asset.maintenance_interval,
+            Asset::ElectricalMeter(asset) => asset.maintenance_interval,
+            Asset::GasMeter(asset) => asset.maintenance_interval,
+            Asset::HotWaterMeter(asset) => asset.maintenance_interval,
+            Asset::ChilledWaterMeter(asset) => asset.maintenance_interval,
+        }
+    }
+
+    pub fn is_meter(&self) -> bool {
+        self.asset_type().is_meter()
+    }
+
+    pub fn is_virtual_meter(&self) -> bool {
+        let is_virtual = match self {
+            Asset::Door(_) => None,
+            Asset::Meter(asset) => asset.is_virtual_meter,
+            Asset::ElectricalMeter(asset) => asset.is_virtual_meter,
+            Asset::GasMeter(asset) => asset.is_virtual_meter,
+            Asset::HotWaterMeter(asset) => asset.is_virtual_meter,
+            Asset::ChilledWaterMeter(asset) => asset.is_virtual_meter,
+        };
+
+        is_virtual.unwrap_or(false)
+    }
+}
+
+impl Resource for Asset {
+    fn id(&self) -> Uuid {
+        Asset::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Asset::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.asset_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        match self {
+            Asset::Door(asset) => vec![(
+                "located_in",
+                asset.located_in.as_deref().unwrap_or_default(),
+            )],
+            Asset::Meter(asset) => vec![
+                (
+                    "located_in",
+                    asset.located_in.as_deref().unwrap_or_default(),
+                ),
+                ("feeds", asset.feeds.as_deref().unwrap_or_default()),
+            ],
+            Asset::ElectricalMeter(asset) => vec![
+                (
+                    "located_in",
+                    asset.located_in.as_deref().unwrap_or_default(),
+                ),
+                ("feeds", asset.feeds.as_deref().unwrap_or_default()),
+            ],
+            Asset::GasMeter(asset) => vec![
+                (
+                    "located_in",
+                    asset.located_in.as_deref().unwrap_or_default(),
+                ),
+                ("feeds", asset.feeds.as_deref().unwrap_or_default()),
+            ],
+            Asset::HotWaterMeter(asset) => vec![
+                (
+                    "located_in",
+                    asset.located_in.as_deref().unwrap_or_default(),
+                ),
+                ("feeds", asset.feeds.as_deref().unwrap_or_default()),
+            ],
+            Asset::ChilledWaterMeter(asset) => vec![
+                (
+                    "located_in",
+                    asset.located_in.as_deref().unwrap_or_default(),
+                ),
+                ("feeds", asset.feeds.as_deref().unwrap_or_default()),
+            ],
+        }
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        match self {
+            Asset::Door(asset) => vec![("located_in", RelationField::Many(&mut asset.located_in))],
+            Asset::Meter(asset) => vec![
+                ("located_in", RelationField::Many(&mut asset.located_in)),
+                ("feeds", RelationField::Many(&mut asset.feeds)),
+            ],
+            Asset::ElectricalMeter(asset) => vec![
+                ("located_in", RelationField::Many(&mut asset.located_in)),
+                ("feeds", RelationField::Many(&mut asset.feeds)),
+            ],
+            Asset::GasMeter(asset) => vec![
+                ("located_in", RelationField::Many(&mut asset.located_in)),
+                ("feeds", RelationField::Many(&mut asset.feeds)),
+            ],
+            Asset::HotWaterMeter(asset) => vec![
+                ("located_in", RelationField::Many(&mut asset.located_in)),
+                ("feeds", RelationField::Many(&mut asset.feeds)),
+            ],
+            Asset::ChilledWaterMeter(asset) => vec![
+                ("located_in", RelationField::Many(&mut asset.located_in)),
+                ("feeds", RelationField::Many(&mut asset.feeds)),
+            ],
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -345,3 +558,18 @@ impl Request<()> for Asset {
         Method::PUT
     }
 }
+
+/// Delete asset by id
+impl Request<()> for AssetId {
+    fn endpoint(&self) -> String {
+        format!("asset/{}", self.deref().id)
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
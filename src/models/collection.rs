@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use uuid::Uuid;
 use wrapi::{http::Method, request::Request};
 
-use super::{Id, Identifier, Relation};
+use super::{Id, Identifier, Relation, RelationField, Resource};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CollectionId(pub Id);
+
+impl Deref for CollectionId {
+    type Target = Id;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum CollectionType {
     Apartment,
@@ -15,6 +26,15 @@ pub enum CollectionType {
     RealEstate,
 }
 
+impl CollectionType {
+    /// Every collection type known to the API
+    pub const ALL: [CollectionType; 3] = [
+        CollectionType::Apartment,
+        CollectionType::Premises,
+        CollectionType::RealEstate,
+    ];
+}
+
 impl ToString for CollectionType {
     fn to_string(&self) -> String {
         match self {
@@ -33,6 +53,88 @@ pub enum Collection {
     RealEstate(RealEstate),
 }
 
+impl Collection {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Collection::Apartment(collection) => collection.id,
+            Collection::Premises(collection) => collection.id,
+            Collection::RealEstate(collection) => collection.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Collection::Apartment(collection) => &collection.name,
+            Collection::Premises(collection) => &collection.name,
+            Collection::RealEstate(collection) => &collection.name,
+        }
+    }
+
+    pub fn collection_type(&self) -> CollectionType {
+        match self {
+            Collection::Apartment(_) => CollectionType::Apartment,
+            Collection::Premises(_) => CollectionType::Premises,
+            Collection::RealEstate(_) => CollectionType::RealEstate,
+        }
+    }
+
+    /// Entities included in the collection
+    pub fn includes(&self) -> &[Relation] {
+        let relations = match self {
+            Collection::Apartment(collection) => &collection.includes,
+            Collection::Premises(collection) => &collection.includes,
+            Collection::RealEstate(collection) => &collection.includes,
+        };
+
+        relations.as_deref().unwrap_or_default()
+    }
+}
+
+impl Resource for Collection {
+    fn id(&self) -> Uuid {
+        Collection::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Collection::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.collection_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        match self {
+            Collection::Apartment(collection) => vec![(
+                "includes",
+                collection.includes.as_deref().unwrap_or_default(),
+            )],
+            Collection::Premises(collection) => vec![(
+                "includes",
+                collection.includes.as_deref().unwrap_or_default(),
+            )],
+            Collection::RealEstate(collection) => vec![(
+                "includes",
+                collection.includes.as_deref().unwrap_or_default(),
+            )],
+        }
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        match self {
+            Collection::Apartment(collection) => {
+                vec![("includes", RelationField::Many(&mut collection.includes))]
+            }
+            Collection::Premises(collection) => {
+                vec![("includes", RelationField::Many(&mut collection.includes))]
+            }
+            Collection::RealEstate(collection) => {
+                vec![("includes", RelationField::Many(&mut collection.includes))]
+            }
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -145,3 +247,18 @@ impl Request<()> for Collection {
         Method::PUT
     }
 }
+
+/// Delete collection by id
+impl Request<()> for CollectionId {
+    fn endpoint(&self) -> String {
+        format!("collection/{}", self.deref().id)
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
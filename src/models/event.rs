@@ -6,7 +6,7 @@ use serde_with::skip_serializing_none;
 use uuid::Uuid;
 use wrapi::{http::Method, request::Request};
 
-use super::{Id, Identifier, Relation};
+use super::{Id, Identifier, Relation, RelationField, Resource};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EventId(pub Id);
@@ -19,11 +19,23 @@ impl Deref for EventId {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum EventType {
     Lease,
     Booking,
+    WorkOrder,
+    Visit,
+}
+
+impl EventType {
+    /// Every event type known to the API
+    pub const ALL: [EventType; 4] = [
+        EventType::Lease,
+        EventType::Booking,
+        EventType::WorkOrder,
+        EventType::Visit,
+    ];
 }
 
 impl ToString for EventType {
@@ -31,15 +43,193 @@ impl ToString for EventType {
         match self {
             EventType::Lease => "lease".into(),
             EventType::Booking => "booking".into(),
+            EventType::WorkOrder => "work_order".into(),
+            EventType::Visit => "visit".into(),
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkOrderStatus {
+    #[default]
+    Open,
+    InProgress,
+    Done,
+}
+
+impl ToString for WorkOrderStatus {
+    fn to_string(&self) -> String {
+        match self {
+            WorkOrderStatus::Open => "open".into(),
+            WorkOrderStatus::InProgress => "in_progress".into(),
+            WorkOrderStatus::Done => "done".into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkOrderPriority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+
+impl ToString for WorkOrderPriority {
+    fn to_string(&self) -> String {
+        match self {
+            WorkOrderPriority::Low => "low".into(),
+            WorkOrderPriority::Normal => "normal".into(),
+            WorkOrderPriority::High => "high".into(),
+            WorkOrderPriority::Urgent => "urgent".into(),
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentFrequency {
+    Monthly,
+    #[default]
+    Quarterly,
+    SemiAnnually,
+    Annually,
+}
+
+impl PaymentFrequency {
+    /// Months covered by one payment
+    pub fn months(&self) -> u32 {
+        match self {
+            PaymentFrequency::Monthly => 1,
+            PaymentFrequency::Quarterly => 3,
+            PaymentFrequency::SemiAnnually => 6,
+            PaymentFrequency::Annually => 12,
+        }
+    }
+}
+
+impl ToString for PaymentFrequency {
+    fn to_string(&self) -> String {
+        match self {
+            PaymentFrequency::Monthly => "monthly".into(),
+            PaymentFrequency::Quarterly => "quarterly".into(),
+            PaymentFrequency::SemiAnnually => "semi_annually".into(),
+            PaymentFrequency::Annually => "annually".into(),
+        }
+    }
+}
+
+/// Rent raised by `rate` every `interval_months` from the lease start
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Indexation {
+    /// Increase per step, `0.02` for 2 %
+    pub rate: f64,
+    pub interval_months: u32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Event {
     Lease(Lease),
     Booking(Booking),
+    WorkOrder(WorkOrder),
+    Visit(Visit),
+}
+
+impl Event {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Event::Lease(event) => event.id,
+            Event::Booking(event) => event.id,
+            Event::WorkOrder(event) => event.id,
+            Event::Visit(event) => event.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Event::Lease(event) => &event.name,
+            Event::Booking(event) => &event.name,
+            Event::WorkOrder(event) => &event.name,
+            Event::Visit(event) => &event.name,
+        }
+    }
+
+    pub fn event_type(&self) -> EventType {
+        match self {
+            Event::Lease(_) => EventType::Lease,
+            Event::Booking(_) => EventType::Booking,
+            Event::WorkOrder(_) => EventType::WorkOrder,
+            Event::Visit(_) => EventType::Visit,
+        }
+    }
+}
+
+impl Resource for Event {
+    fn id(&self) -> Uuid {
+        Event::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Event::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.event_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        match self {
+            Event::Lease(event) => vec![
+                ("leasee", event.leasee.as_deref().unwrap_or_default()),
+                ("leasor", event.leasor.as_deref().unwrap_or_default()),
+                ("lease_of", event.lease_of.as_deref().unwrap_or_default()),
+            ],
+            Event::Booking(event) => vec![
+                ("booked_by", event.booked_by.as_slice()),
+                ("lease", event.lease.as_slice()),
+                ("room", event.room.as_slice()),
+            ],
+            Event::WorkOrder(event) => vec![
+                ("asset", event.asset.as_slice()),
+                ("assigned_to", event.assigned_to.as_slice()),
+            ],
+            Event::Visit(event) => vec![
+                ("visitor", event.visitor.as_slice()),
+                ("host", event.host.as_slice()),
+                ("location", event.location.as_slice()),
+            ],
+        }
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        match self {
+            Event::Lease(event) => vec![
+                ("leasee", RelationField::Many(&mut event.leasee)),
+                ("leasor", RelationField::Many(&mut event.leasor)),
+                ("lease_of", RelationField::Many(&mut event.lease_of)),
+            ],
+            Event::Booking(event) => vec![
+                ("booked_by", RelationField::One(&mut event.booked_by)),
+                ("lease", RelationField::One(&mut event.lease)),
+                ("room", RelationField::One(&mut event.room)),
+            ],
+            Event::WorkOrder(event) => vec![
+                ("asset", RelationField::One(&mut event.asset)),
+                ("assigned_to", RelationField::One(&mut event.assigned_to)),
+            ],
+            Event::Visit(event) => vec![
+                ("visitor", RelationField::One(&mut event.visitor)),
+                ("host", RelationField::One(&mut event.host)),
+                ("location", RelationField::One(&mut event.location)),
+            ],
+        }
+    }
 }
 
 #[skip_serializing_none]
@@ -54,6 +244,24 @@ pub struct Lease {
     pub leasee: Option<Vec<Relation>>,
     pub leasor: Option<Vec<Relation>>,
     pub lease_of: Option<Vec<Relation>>,
+    /// Rent per year before indexation, in minor units of the currency
+    pub annual_rent: Option<i64>,
+    /// ISO 4217 code, e.g. `SEK`
+    pub currency: Option<String>,
+    pub payment_frequency: Option<PaymentFrequency>,
+    pub indexation: Option<Indexation>,
+    /// In minor units of the currency
+    pub deposit: Option<i64>,
+    pub notice_period_months: Option<u32>,
+}
+
+impl Lease {
+    /// Whether the lease runs at `at`, open-ended leases never end
+    // `Option::is_none_or` needs Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn is_active_at(&self, at: DateTime<Utc>) -> bool {
+        self.start <= at && self.end.map_or(true, |end| at < end)
+    }
 }
 
 #[skip_serializing_none]
@@ -67,9 +275,100 @@ pub struct Booking {
     pub identifiers: Option<Vec<Identifier>>,
     pub booked_by: Option<Relation>,
     pub lease: Option<Relation>,
+    /// Room, entrance or desk booked
     pub room: Option<Relation>,
 }
 
+/// Integration of the identifier recording when a booking was checked in to
+pub const CHECK_IN_INTEGRATION: &str = "check_in";
+/// Integration of the identifier recording when a booking was released
+pub const RELEASE_INTEGRATION: &str = "release";
+
+impl Booking {
+    /// Time recorded by the identifier of `integration`
+    fn recorded(&self, integration: &str) -> Option<DateTime<Utc>> {
+        self.identifiers
+            .iter()
+            .flatten()
+            .find(|identifier| identifier.integration == integration)
+            .and_then(|identifier| DateTime::parse_from_rfc3339(&identifier.external_id).ok())
+            .map(|at| at.with_timezone(&Utc))
+    }
+
+    /// Record a time as the identifier of `integration`,
+    /// keeping a time already recorded
+    fn record(&mut self, integration: &str, at: DateTime<Utc>) {
+        if self.recorded(integration).is_none() {
+            self.identifiers
+                .get_or_insert_with(Vec::new)
+                .push(Identifier {
+                    integration: integration.into(),
+                    external_id: at.to_rfc3339(),
+                });
+        }
+    }
+
+    /// Time the room was taken
+    pub fn checked_in_at(&self) -> Option<DateTime<Utc>> {
+        self.recorded(CHECK_IN_INTEGRATION)
+    }
+
+    /// Time the room was given back, freeing it for the rest of the booking
+    pub fn released_at(&self) -> Option<DateTime<Utc>> {
+        self.recorded(RELEASE_INTEGRATION)
+    }
+
+    pub fn mark_checked_in(&mut self, at: DateTime<Utc>) {
+        self.record(CHECK_IN_INTEGRATION, at);
+    }
+
+    pub fn mark_released(&mut self, at: DateTime<Utc>) {
+        self.record(RELEASE_INTEGRATION, at);
+    }
+
+    /// End of the booking, or the time it was released if earlier
+    pub fn effective_end(&self) -> DateTime<Utc> {
+        self.released_at()
+            .map_or(self.end, |released| released.clamp(self.start, self.end))
+    }
+}
+
+/// Maintenance work on an asset, assigned to a company or person
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkOrder {
+    pub id: Uuid,
+    pub name: String,
+    pub identifiers: Option<Vec<Identifier>>,
+    #[serde(default)]
+    pub status: WorkOrderStatus,
+    pub priority: Option<WorkOrderPriority>,
+    pub scheduled_start: Option<DateTime<Utc>>,
+    pub scheduled_end: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub asset: Option<Relation>,
+    pub assigned_to: Option<Relation>,
+}
+
+/// Visit of an external person hosted by a person,
+/// at a building or entrance
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Visit {
+    pub id: Uuid,
+    pub name: String,
+    pub identifiers: Option<Vec<Identifier>>,
+    pub expected_arrival: DateTime<Utc>,
+    pub expected_departure: DateTime<Utc>,
+    pub arrived_at: Option<DateTime<Utc>>,
+    pub departed_at: Option<DateTime<Utc>>,
+    pub visitor: Option<Relation>,
+    pub host: Option<Relation>,
+    pub location: Option<Relation>,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -83,6 +382,12 @@ pub enum NewEvent {
         leasee: Option<Vec<Id>>,
         leasor: Option<Vec<Id>>,
         lease_of: Option<Vec<Id>>,
+        annual_rent: Option<i64>,
+        currency: Option<String>,
+        payment_frequency: Option<PaymentFrequency>,
+        indexation: Option<Indexation>,
+        deposit: Option<i64>,
+        notice_period_months: Option<u32>,
     },
     #[serde(rename_all = "camelCase")]
     Booking {
@@ -94,6 +399,28 @@ pub enum NewEvent {
         lease: Id,
         room: Option<Id>,
     },
+    #[serde(rename_all = "camelCase")]
+    WorkOrder {
+        name: String,
+        identifiers: Option<Vec<Identifier>>,
+        status: Option<WorkOrderStatus>,
+        priority: Option<WorkOrderPriority>,
+        scheduled_start: Option<String>,
+        scheduled_end: Option<String>,
+        completed_at: Option<String>,
+        asset: Id,
+        assigned_to: Option<Id>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Visit {
+        name: String,
+        identifiers: Option<Vec<Identifier>>,
+        expected_arrival: String,
+        expected_departure: String,
+        visitor: Id,
+        host: Option<Id>,
+        location: Option<Id>,
+    },
 }
 
 /// Get events of provided type
@@ -141,6 +468,17 @@ impl Request<Event> for Id {
     }
 }
 
+/// Replace event by id
+impl Request<()> for Event {
+    fn endpoint(&self) -> String {
+        format!("event/{}", self.id())
+    }
+
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+}
+
 /// Delete event by id
 impl Request<()> for EventId {
     fn endpoint(&self) -> String {
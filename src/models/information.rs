@@ -1,13 +1,24 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use uuid::Uuid;
 use wrapi::{http::Method, request::Request};
 
-use super::{Id, Identifier};
+use super::{Id, Identifier, Relation, RelationField, Resource};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InformationId(pub Id);
+
+impl Deref for InformationId {
+    type Target = Id;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum InformationType {
     ArchitectureArea,
@@ -15,6 +26,15 @@ pub enum InformationType {
     PostalAddress,
 }
 
+impl InformationType {
+    /// Every information type known to the API
+    pub const ALL: [InformationType; 3] = [
+        InformationType::ArchitectureArea,
+        InformationType::ArchitectureCapacity,
+        InformationType::PostalAddress,
+    ];
+}
+
 impl ToString for InformationType {
     fn to_string(&self) -> String {
         match self {
@@ -33,6 +53,54 @@ pub enum Information {
     PostalAddress(PostalAddress),
 }
 
+impl Information {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Information::ArchitectureArea(information) => information.id,
+            Information::ArchitectureCapacity(information) => information.id,
+            Information::PostalAddress(information) => information.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Information::ArchitectureArea(information) => &information.name,
+            Information::ArchitectureCapacity(information) => &information.name,
+            Information::PostalAddress(information) => &information.name,
+        }
+    }
+
+    pub fn information_type(&self) -> InformationType {
+        match self {
+            Information::ArchitectureArea(_) => InformationType::ArchitectureArea,
+            Information::ArchitectureCapacity(_) => InformationType::ArchitectureCapacity,
+            Information::PostalAddress(_) => InformationType::PostalAddress,
+        }
+    }
+}
+
+impl Resource for Information {
+    fn id(&self) -> Uuid {
+        Information::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Information::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.information_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        Vec::new()
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        Vec::new()
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -164,3 +232,18 @@ impl Request<()> for Information {
         Method::PUT
     }
 }
+
+/// Delete information by id
+impl Request<()> for InformationId {
+    fn endpoint(&self) -> String {
+        format!("information/{}", self.deref().id)
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
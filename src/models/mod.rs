@@ -2,7 +2,10 @@ use std::str::{self, FromStr};
 
 use agent::{Agent, AgentType};
 use asset::{Asset, AssetType};
+use collection::Collection;
 use event::{Event, EventType};
+use information::Information;
+use point::Point;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use space::{Space, SpaceType};
@@ -16,6 +19,8 @@ pub mod auth;
 pub mod collection;
 pub mod event;
 pub mod information;
+pub mod point;
+pub mod reading;
 pub mod space;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -46,6 +51,17 @@ pub struct Relation {
     pub name: Option<String>,
 }
 
+impl Relation {
+    /// Relation to `target` with its current type and name cached
+    pub fn to(target: &impl Resource) -> Self {
+        Self {
+            id: target.id(),
+            rtype: Some(target.rtype()),
+            name: Some(target.name().to_string()),
+        }
+    }
+}
+
 impl From<Uuid> for Relation {
     fn from(value: Uuid) -> Self {
         Self {
@@ -146,6 +162,26 @@ impl From<Event> for Relation {
                 rtype: Some(EventType::Booking.to_string()),
                 name: Some(event.name),
             },
+            Event::WorkOrder(event) => Self {
+                id: event.id,
+                rtype: Some(EventType::WorkOrder.to_string()),
+                name: Some(event.name),
+            },
+            Event::Visit(event) => Self {
+                id: event.id,
+                rtype: Some(EventType::Visit.to_string()),
+                name: Some(event.name),
+            },
+        }
+    }
+}
+
+impl From<Point> for Relation {
+    fn from(value: Point) -> Self {
+        Self {
+            id: value.id(),
+            rtype: Some(value.point_type().to_string()),
+            name: Some(value.name().to_string()),
         }
     }
 }
@@ -178,6 +214,216 @@ impl From<Space> for Relation {
                 rtype: Some(SpaceType::Entrance.to_string()),
                 name: Some(space.name),
             },
+            Space::Desk(space) => Self {
+                id: space.id,
+                rtype: Some(SpaceType::Desk.to_string()),
+                name: Some(space.name),
+            },
+        }
+    }
+}
+
+/// Relation valued field of a resource
+#[derive(Debug)]
+pub enum RelationField<'a> {
+    One(&'a mut Option<Relation>),
+    Many(&'a mut Option<Vec<Relation>>),
+}
+
+impl RelationField<'_> {
+    pub fn relations(&self) -> &[Relation] {
+        match self {
+            RelationField::One(relation) => relation.as_slice(),
+            RelationField::Many(relations) => relations.as_deref().unwrap_or_default(),
+        }
+    }
+
+    pub fn relations_mut(&mut self) -> &mut [Relation] {
+        match self {
+            RelationField::One(relation) => relation.as_mut_slice(),
+            RelationField::Many(relations) => relations.as_deref_mut().unwrap_or_default(),
+        }
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.relations().iter().any(|relation| relation.id == *id)
+    }
+
+    /// Keep only relations matching `f`, returns the number removed
+    pub fn retain(&mut self, mut f: impl FnMut(&Relation) -> bool) -> usize {
+        match self {
+            RelationField::One(relation) => match relation {
+                Some(r) if !f(r) => {
+                    **relation = None;
+                    1
+                }
+                _ => 0,
+            },
+            RelationField::Many(relations) => {
+                let Some(relations) = relations.as_mut() else {
+                    return 0;
+                };
+
+                let len = relations.len();
+                relations.retain(f);
+
+                len - relations.len()
+            }
+        }
+    }
+
+    /// Add a relation unless one to the same id exists,
+    /// a single valued field is overwritten
+    pub fn insert(&mut self, relation: Relation) -> bool {
+        if self.contains(&relation.id) {
+            return false;
+        }
+
+        match self {
+            RelationField::One(current) => **current = Some(relation),
+            RelationField::Many(relations) => relations.get_or_insert_with(Vec::new).push(relation),
+        }
+
+        true
+    }
+}
+
+/// Common view of every resource kind
+pub trait Resource {
+    fn id(&self) -> Uuid;
+
+    fn name(&self) -> &str;
+
+    /// Type as cached in `Relation::rtype`
+    fn rtype(&self) -> String;
+
+    /// Relation valued fields by field name
+    fn relations(&self) -> Vec<(&'static str, &[Relation])>;
+
+    /// Mutable relation valued fields by field name
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)>;
+
+    /// Mutable relation valued field with the provided name
+    fn relation_field(&mut self, name: &str) -> Option<RelationField<'_>> {
+        self.relation_fields()
+            .into_iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, field)| field)
+    }
+}
+
+/// Any resource that can be the target of a relation
+#[derive(Debug, Clone)]
+pub enum Entity {
+    Agent(Agent),
+    Asset(Asset),
+    Collection(Collection),
+    Event(Event),
+    Information(Information),
+    Point(Point),
+    Space(Space),
+}
+
+impl From<Agent> for Entity {
+    fn from(value: Agent) -> Self {
+        Entity::Agent(value)
+    }
+}
+
+impl From<Asset> for Entity {
+    fn from(value: Asset) -> Self {
+        Entity::Asset(value)
+    }
+}
+
+impl From<Collection> for Entity {
+    fn from(value: Collection) -> Self {
+        Entity::Collection(value)
+    }
+}
+
+impl From<Event> for Entity {
+    fn from(value: Event) -> Self {
+        Entity::Event(value)
+    }
+}
+
+impl From<Information> for Entity {
+    fn from(value: Information) -> Self {
+        Entity::Information(value)
+    }
+}
+
+impl From<Point> for Entity {
+    fn from(value: Point) -> Self {
+        Entity::Point(value)
+    }
+}
+
+impl From<Space> for Entity {
+    fn from(value: Space) -> Self {
+        Entity::Space(value)
+    }
+}
+
+impl Resource for Entity {
+    fn id(&self) -> Uuid {
+        match self {
+            Entity::Agent(entity) => entity.id(),
+            Entity::Asset(entity) => entity.id(),
+            Entity::Collection(entity) => entity.id(),
+            Entity::Event(entity) => entity.id(),
+            Entity::Information(entity) => entity.id(),
+            Entity::Point(entity) => entity.id(),
+            Entity::Space(entity) => entity.id(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Entity::Agent(entity) => entity.name(),
+            Entity::Asset(entity) => entity.name(),
+            Entity::Collection(entity) => entity.name(),
+            Entity::Event(entity) => entity.name(),
+            Entity::Information(entity) => entity.name(),
+            Entity::Point(entity) => entity.name(),
+            Entity::Space(entity) => entity.name(),
+        }
+    }
+
+    fn rtype(&self) -> String {
+        match self {
+            Entity::Agent(entity) => entity.rtype(),
+            Entity::Asset(entity) => entity.rtype(),
+            Entity::Collection(entity) => entity.rtype(),
+            Entity::Event(entity) => entity.rtype(),
+            Entity::Information(entity) => entity.rtype(),
+            Entity::Point(entity) => entity.rtype(),
+            Entity::Space(entity) => entity.rtype(),
+        }
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        match self {
+            Entity::Agent(entity) => entity.relations(),
+            Entity::Asset(entity) => entity.relations(),
+            Entity::Collection(entity) => entity.relations(),
+            Entity::Event(entity) => entity.relations(),
+            Entity::Information(entity) => entity.relations(),
+            Entity::Point(entity) => entity.relations(),
+            Entity::Space(entity) => entity.relations(),
+        }
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        match self {
+            Entity::Agent(entity) => entity.relation_fields(),
+            Entity::Asset(entity) => entity.relation_fields(),
+            Entity::Collection(entity) => entity.relation_fields(),
+            Entity::Event(entity) => entity.relation_fields(),
+            Entity::Information(entity) => entity.relation_fields(),
+            Entity::Point(entity) => entity.relation_fields(),
+            Entity::Space(entity) => entity.relation_fields(),
         }
     }
 }
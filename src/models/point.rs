@@ -0,0 +1,277 @@
+use std::{collections::HashMap, ops::Deref};
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use uuid::Uuid;
+use wrapi::{http::Method, request::Request};
+
+use super::{Id, Identifier, Relation, RelationField, Resource};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PointId(pub Id);
+
+impl Deref for PointId {
+    type Target = Id;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PointType {
+    Sensor,
+    Setpoint,
+    Alarm,
+}
+
+impl PointType {
+    /// Every point type known to the API
+    pub const ALL: [PointType; 3] = [PointType::Sensor, PointType::Setpoint, PointType::Alarm];
+}
+
+impl ToString for PointType {
+    fn to_string(&self) -> String {
+        match self {
+            PointType::Sensor => "sensor".into(),
+            PointType::Setpoint => "setpoint".into(),
+            PointType::Alarm => "alarm".into(),
+        }
+    }
+}
+
+/// Type of the values a point reports
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PointDataType {
+    Boolean,
+    Integer,
+    Float,
+    String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Point {
+    Sensor(Sensor),
+    Setpoint(Setpoint),
+    Alarm(Alarm),
+}
+
+impl Point {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Point::Sensor(point) => point.id,
+            Point::Setpoint(point) => point.id,
+            Point::Alarm(point) => point.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Point::Sensor(point) => &point.name,
+            Point::Setpoint(point) => &point.name,
+            Point::Alarm(point) => &point.name,
+        }
+    }
+
+    pub fn point_type(&self) -> PointType {
+        match self {
+            Point::Sensor(_) => PointType::Sensor,
+            Point::Setpoint(_) => PointType::Setpoint,
+            Point::Alarm(_) => PointType::Alarm,
+        }
+    }
+
+    /// Spaces and assets the point belongs to
+    pub fn is_point_of(&self) -> &[Relation] {
+        let relations = match self {
+            Point::Sensor(point) => &point.is_point_of,
+            Point::Setpoint(point) => &point.is_point_of,
+            Point::Alarm(point) => &point.is_point_of,
+        };
+
+        relations.as_deref().unwrap_or_default()
+    }
+}
+
+impl Resource for Point {
+    fn id(&self) -> Uuid {
+        Point::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Point::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.point_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        vec![("is_point_of", self.is_point_of())]
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        let relations = match self {
+            Point::Sensor(point) => &mut point.is_point_of,
+            Point::Setpoint(point) => &mut point.is_point_of,
+            Point::Alarm(point) => &mut point.is_point_of,
+        };
+
+        vec![("is_point_of", RelationField::Many(relations))]
+    }
+}
+
+/// Measured value, e.g. temperature or occupancy
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Sensor {
+    pub id: Uuid,
+    pub name: String,
+    pub identifiers: Option<Vec<Identifier>>,
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    pub data_type: Option<PointDataType>,
+    pub writable: Option<bool>,
+    pub is_point_of: Option<Vec<Relation>>,
+}
+
+/// Target value written to a controller
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Setpoint {
+    pub id: Uuid,
+    pub name: String,
+    pub identifiers: Option<Vec<Identifier>>,
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    pub data_type: Option<PointDataType>,
+    pub writable: Option<bool>,
+    pub is_point_of: Option<Vec<Relation>>,
+}
+
+/// Alarm state raised by a device
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Alarm {
+    pub id: Uuid,
+    pub name: String,
+    pub identifiers: Option<Vec<Identifier>>,
+    pub quantity: Option<String>,
+    pub unit: Option<String>,
+    pub data_type: Option<PointDataType>,
+    pub writable: Option<bool>,
+    pub is_point_of: Option<Vec<Relation>>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NewPoint {
+    #[serde(rename_all = "camelCase")]
+    Sensor {
+        name: String,
+        identifiers: Option<Vec<Identifier>>,
+        quantity: Option<String>,
+        unit: Option<String>,
+        data_type: Option<PointDataType>,
+        writable: Option<bool>,
+        is_point_of: Option<Vec<Relation>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Setpoint {
+        name: String,
+        identifiers: Option<Vec<Identifier>>,
+        quantity: Option<String>,
+        unit: Option<String>,
+        data_type: Option<PointDataType>,
+        writable: Option<bool>,
+        is_point_of: Option<Vec<Relation>>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Alarm {
+        name: String,
+        identifiers: Option<Vec<Identifier>>,
+        quantity: Option<String>,
+        unit: Option<String>,
+        data_type: Option<PointDataType>,
+        writable: Option<bool>,
+        is_point_of: Option<Vec<Relation>>,
+    },
+}
+
+/// Get points of provided type
+impl Request<Vec<Point>> for PointType {
+    fn endpoint(&self) -> String {
+        "point".into()
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn query(&self) -> Option<HashMap<String, String>> {
+        Some(HashMap::from([("type".to_string(), self.to_string())]))
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
+
+/// Create point
+impl Request<Id> for NewPoint {
+    fn endpoint(&self) -> String {
+        "point".into()
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+}
+
+/// Get point by id
+impl Request<Point> for Id {
+    fn endpoint(&self) -> String {
+        format!("point/{}", self.id)
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
+
+/// Replace point by id
+impl Request<()> for Point {
+    fn endpoint(&self) -> String {
+        format!("point/{}", self.id())
+    }
+
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+}
+
+/// Delete point by id
+impl Request<()> for PointId {
+    fn endpoint(&self) -> String {
+        format!("point/{}", self.deref().id)
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
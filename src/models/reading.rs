@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Datelike, Duration, Months, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wrapi::{http::Method, request::Request};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingQuality {
+    #[default]
+    Good,
+    Estimated,
+    Suspect,
+    Missing,
+}
+
+/// Consumption measured by a meter over the interval
+/// ending at `timestamp`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Reading {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+    pub unit: String,
+    #[serde(default)]
+    pub quality: ReadingQuality,
+}
+
+/// Batch of readings for a meter
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NewReadings {
+    #[serde(skip)]
+    pub meter: Uuid,
+    pub readings: Vec<Reading>,
+}
+
+/// Readings of a meter with `from <= timestamp < to`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReadingQuery {
+    pub meter: Uuid,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    Hourly,
+    Daily,
+    Monthly,
+}
+
+impl Resolution {
+    /// Start of the bucket containing `timestamp`
+    pub fn truncate(&self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let (year, month, day, hour) = match self {
+            Resolution::Hourly => (
+                timestamp.year(),
+                timestamp.month(),
+                timestamp.day(),
+                timestamp.hour(),
+            ),
+            Resolution::Daily => (timestamp.year(), timestamp.month(), timestamp.day(), 0),
+            Resolution::Monthly => (timestamp.year(), timestamp.month(), 1, 0),
+        };
+
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0)
+            .single()
+            .unwrap_or(timestamp)
+    }
+
+    /// Start of the bucket following the one starting at `start`
+    pub fn next(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Resolution::Hourly => start + Duration::hours(1),
+            Resolution::Daily => start + Duration::days(1),
+            Resolution::Monthly => start + Months::new(1),
+        }
+    }
+}
+
+/// Readings of one unit aggregated over `start < timestamp <= end`,
+/// the consumption of `start <= t < end`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingBucket {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub unit: String,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+/// Aggregate readings into buckets, ordered by start and unit
+///
+/// A reading falls in the bucket its interval ends in, so the
+/// reading at midnight counts for the previous day, and readings
+/// with `from < timestamp <= to` fill the buckets from `from` to
+/// `to`. Readings flagged `Missing` are skipped.
+pub fn aggregate(readings: &[Reading], resolution: Resolution) -> Vec<ReadingBucket> {
+    let mut buckets: BTreeMap<(DateTime<Utc>, &str), ReadingBucket> = BTreeMap::new();
+
+    for reading in readings {
+        if reading.quality == ReadingQuality::Missing {
+            continue;
+        }
+
+        let start = resolution.truncate(reading.timestamp - Duration::nanoseconds(1));
+
+        buckets
+            .entry((start, &reading.unit))
+            .and_modify(|bucket| {
+                bucket.sum += reading.value;
+                bucket.min = bucket.min.min(reading.value);
+                bucket.max = bucket.max.max(reading.value);
+                bucket.count += 1;
+            })
+            .or_insert_with(|| ReadingBucket {
+                start,
+                end: resolution.next(start),
+                unit: reading.unit.clone(),
+                sum: reading.value,
+                min: reading.value,
+                max: reading.value,
+                count: 1,
+            });
+    }
+
+    buckets.into_values().collect()
+}
+
+/// Push readings for a meter
+impl Request<()> for NewReadings {
+    fn endpoint(&self) -> String {
+        format!("asset/{}/readings", self.meter)
+    }
+
+    fn method(&self) -> Method {
+        Method::POST
+    }
+}
+
+/// Get readings for a meter in a time range
+impl Request<Vec<Reading>> for ReadingQuery {
+    fn endpoint(&self) -> String {
+        format!("asset/{}/readings", self.meter)
+    }
+
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn query(&self) -> Option<HashMap<String, String>> {
+        Some(HashMap::from([
+            ("from".to_string(), self.from.to_rfc3339()),
+            ("to".to_string(), self.to.to_rfc3339()),
+        ]))
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(timestamp: DateTime<Utc>, value: f64, quality: ReadingQuality) -> Reading {
+        Reading {
+            timestamp,
+            value,
+            unit: "kWh".into(),
+            quality,
+        }
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn reading_counts_for_the_interval_it_ends() {
+        let readings = [
+            reading(at(1, 1), 1., ReadingQuality::Good),
+            reading(at(1, 2), 2., ReadingQuality::Good),
+            reading(at(2, 0), 4., ReadingQuality::Good),
+        ];
+
+        let hourly = aggregate(&readings, Resolution::Hourly);
+        let starts: Vec<_> = hourly.iter().map(|bucket| bucket.start).collect();
+        assert_eq!(starts, [at(1, 0), at(1, 1), at(1, 23)]);
+
+        let daily = aggregate(&readings, Resolution::Daily);
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].start, at(1, 0));
+        assert_eq!(daily[0].end, at(2, 0));
+        assert_eq!(daily[0].sum, 7.);
+        assert_eq!(daily[0].count, 3);
+    }
+
+    #[test]
+    fn missing_readings_are_skipped() {
+        let readings = [
+            reading(at(1, 1), 1., ReadingQuality::Good),
+            reading(at(1, 1), 5., ReadingQuality::Missing),
+            reading(at(1, 1), 3., ReadingQuality::Estimated),
+        ];
+
+        let buckets = aggregate(&readings, Resolution::Hourly);
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].sum, 4.);
+        assert_eq!(buckets[0].min, 1.);
+        assert_eq!(buckets[0].max, 3.);
+    }
+
+    #[test]
+    fn buckets_are_split_by_unit() {
+        let mut water = reading(at(1, 1), 2., ReadingQuality::Good);
+        water.unit = "m3".into();
+
+        let buckets = aggregate(
+            &[reading(at(1, 1), 1., ReadingQuality::Good), water],
+            Resolution::Monthly,
+        );
+        let units: Vec<_> = buckets.iter().map(|bucket| bucket.unit.as_str()).collect();
+        assert_eq!(units, ["kWh", "m3"]);
+        assert_eq!(
+            buckets[0].end,
+            Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap()
+        );
+    }
+}
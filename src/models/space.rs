@@ -1,11 +1,22 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Deref};
 
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use uuid::Uuid;
 use wrapi::{http::Method, request::Request};
 
-use super::{Id, Identifier, Relation};
+use super::{Id, Identifier, Relation, RelationField, Resource};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpaceId(pub Id);
+
+impl Deref for SpaceId {
+    type Target = Id;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
 
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -34,7 +45,7 @@ pub struct Capacity {
     pub seating_capacity: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum SpaceType {
     AccessControlZone,
@@ -42,6 +53,19 @@ pub enum SpaceType {
     Level,
     Room,
     Entrance,
+    Desk,
+}
+
+impl SpaceType {
+    /// Every space type known to the API
+    pub const ALL: [SpaceType; 6] = [
+        SpaceType::AccessControlZone,
+        SpaceType::Building,
+        SpaceType::Level,
+        SpaceType::Room,
+        SpaceType::Entrance,
+        SpaceType::Desk,
+    ];
 }
 
 impl ToString for SpaceType {
@@ -52,6 +76,7 @@ impl ToString for SpaceType {
             SpaceType::Level => "level".to_string(),
             SpaceType::Room => "room".to_string(),
             SpaceType::Entrance => "entrance".to_string(),
+            SpaceType::Desk => "desk".to_string(),
         }
     }
 }
@@ -64,6 +89,305 @@ pub enum Space {
     Level(Level),
     Room(Room),
     Entrance(Entrance),
+    Desk(Desk),
+}
+
+impl Space {
+    pub fn id(&self) -> Uuid {
+        match self {
+            Space::AccessControlZone(space) => space.id,
+            Space::Building(space) => space.id,
+            Space::Level(space) => space.id,
+            Space::Room(space) => space.id,
+            Space::Entrance(space) => space.id,
+            Space::Desk(space) => space.id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Space::AccessControlZone(space) => &space.name,
+            Space::Building(space) => &space.name,
+            Space::Level(space) => &space.name,
+            Space::Room(space) => &space.name,
+            Space::Entrance(space) => &space.name,
+            Space::Desk(space) => &space.name,
+        }
+    }
+
+    pub fn space_type(&self) -> SpaceType {
+        match self {
+            Space::AccessControlZone(_) => SpaceType::AccessControlZone,
+            Space::Building(_) => SpaceType::Building,
+            Space::Level(_) => SpaceType::Level,
+            Space::Room(_) => SpaceType::Room,
+            Space::Entrance(_) => SpaceType::Entrance,
+            Space::Desk(_) => SpaceType::Desk,
+        }
+    }
+
+    /// Spaces this space is made up of
+    pub fn has_part(&self) -> &[Relation] {
+        let relations = match self {
+            Space::AccessControlZone(space) => &space.has_part,
+            Space::Building(space) => &space.has_part,
+            Space::Level(space) => &space.has_part,
+            Space::Room(space) => &space.has_part,
+            Space::Entrance(space) => &space.has_part,
+            Space::Desk(space) => &space.has_part,
+        };
+
+        relations.as_deref().unwrap_or_default()
+    }
+
+    /// Spaces this space is a part of
+    pub fn is_part_of(&self) -> &[Relation] {
+        let relations = match self {
+            Space::AccessControlZone(space) => &space.is_part_of,
+            Space::Building(space) => &space.is_part_of,
+            Space::Level(space) => &space.is_part_of,
+            Space::Room(space) => &space.is_part_of,
+            Space::Entrance(space) => &space.is_part_of,
+            Space::Desk(space) => &space.is_part_of,
+        };
+
+        relations.as_deref().unwrap_or_default()
+    }
+
+    /// Level number, only set for levels
+    pub fn level_number(&self) -> Option<u32> {
+        match self {
+            Space::Level(space) => space.level_number,
+            _ => None,
+        }
+    }
+
+    pub fn area(&self) -> Option<&Area> {
+        match self {
+            Space::AccessControlZone(space) => space.area.as_ref(),
+            Space::Building(space) => space.area.as_ref(),
+            Space::Level(space) => space.area.as_ref(),
+            Space::Room(space) => space.area.as_ref(),
+            Space::Entrance(space) => space.area.as_ref(),
+            Space::Desk(space) => space.area.as_ref(),
+        }
+    }
+
+    pub fn capacity(&self) -> Option<&Capacity> {
+        match self {
+            Space::AccessControlZone(space) => space.capacity.as_ref(),
+            Space::Building(space) => space.capacity.as_ref(),
+            Space::Level(space) => space.capacity.as_ref(),
+            Space::Room(space) => space.capacity.as_ref(),
+            Space::Entrance(space) => space.capacity.as_ref(),
+            Space::Desk(space) => space.capacity.as_ref(),
+        }
+    }
+
+    /// Whether the space can be booked, only rooms,
+    /// entrances and desks can
+    pub fn is_bookable(&self) -> bool {
+        let bookable = match self {
+            Space::Room(space) => space.bookable,
+            Space::Entrance(space) => space.bookable,
+            Space::Desk(space) => space.bookable,
+            _ => None,
+        };
+
+        bookable.unwrap_or_default()
+    }
+}
+
+impl Resource for Space {
+    fn id(&self) -> Uuid {
+        Space::id(self)
+    }
+
+    fn name(&self) -> &str {
+        Space::name(self)
+    }
+
+    fn rtype(&self) -> String {
+        self.space_type().to_string()
+    }
+
+    fn relations(&self) -> Vec<(&'static str, &[Relation])> {
+        match self {
+            Space::AccessControlZone(space) => vec![
+                ("has_part", space.has_part.as_deref().unwrap_or_default()),
+                (
+                    "is_part_of",
+                    space.is_part_of.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "is_location_of",
+                    space.is_location_of.as_deref().unwrap_or_default(),
+                ),
+                ("address", space.address.as_deref().unwrap_or_default()),
+                (
+                    "included_in",
+                    space.included_in.as_deref().unwrap_or_default(),
+                ),
+                ("has_point", space.has_point.as_deref().unwrap_or_default()),
+            ],
+            Space::Building(space) => vec![
+                ("has_part", space.has_part.as_deref().unwrap_or_default()),
+                (
+                    "is_part_of",
+                    space.is_part_of.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "is_location_of",
+                    space.is_location_of.as_deref().unwrap_or_default(),
+                ),
+                ("address", space.address.as_deref().unwrap_or_default()),
+                (
+                    "included_in",
+                    space.included_in.as_deref().unwrap_or_default(),
+                ),
+                ("has_point", space.has_point.as_deref().unwrap_or_default()),
+            ],
+            Space::Level(space) => vec![
+                ("has_part", space.has_part.as_deref().unwrap_or_default()),
+                (
+                    "is_part_of",
+                    space.is_part_of.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "is_location_of",
+                    space.is_location_of.as_deref().unwrap_or_default(),
+                ),
+                ("address", space.address.as_deref().unwrap_or_default()),
+                (
+                    "included_in",
+                    space.included_in.as_deref().unwrap_or_default(),
+                ),
+                ("has_point", space.has_point.as_deref().unwrap_or_default()),
+            ],
+            Space::Room(space) => vec![
+                ("has_part", space.has_part.as_deref().unwrap_or_default()),
+                (
+                    "is_part_of",
+                    space.is_part_of.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "is_location_of",
+                    space.is_location_of.as_deref().unwrap_or_default(),
+                ),
+                ("address", space.address.as_deref().unwrap_or_default()),
+                (
+                    "included_in",
+                    space.included_in.as_deref().unwrap_or_default(),
+                ),
+                ("has_point", space.has_point.as_deref().unwrap_or_default()),
+            ],
+            Space::Entrance(space) => vec![
+                ("has_part", space.has_part.as_deref().unwrap_or_default()),
+                (
+                    "is_part_of",
+                    space.is_part_of.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "is_location_of",
+                    space.is_location_of.as_deref().unwrap_or_default(),
+                ),
+                ("address", space.address.as_deref().unwrap_or_default()),
+                (
+                    "included_in",
+                    space.included_in.as_deref().unwrap_or_default(),
+                ),
+                ("has_point", space.has_point.as_deref().unwrap_or_default()),
+            ],
+            Space::Desk(space) => vec![
+                ("has_part", space.has_part.as_deref().unwrap_or_default()),
+                (
+                    "is_part_of",
+                    space.is_part_of.as_deref().unwrap_or_default(),
+                ),
+                (
+                    "is_location_of",
+                    space.is_location_of.as_deref().unwrap_or_default(),
+                ),
+                ("address", space.address.as_deref().unwrap_or_default()),
+                (
+                    "included_in",
+                    space.included_in.as_deref().unwrap_or_default(),
+                ),
+                ("has_point", space.has_point.as_deref().unwrap_or_default()),
+            ],
+        }
+    }
+
+    fn relation_fields(&mut self) -> Vec<(&'static str, RelationField<'_>)> {
+        match self {
+            Space::AccessControlZone(space) => vec![
+                ("has_part", RelationField::Many(&mut space.has_part)),
+                ("is_part_of", RelationField::Many(&mut space.is_part_of)),
+                (
+                    "is_location_of",
+                    RelationField::Many(&mut space.is_location_of),
+                ),
+                ("address", RelationField::Many(&mut space.address)),
+                ("included_in", RelationField::Many(&mut space.included_in)),
+                ("has_point", RelationField::Many(&mut space.has_point)),
+            ],
+            Space::Building(space) => vec![
+                ("has_part", RelationField::Many(&mut space.has_part)),
+                ("is_part_of", RelationField::Many(&mut space.is_part_of)),
+                (
+                    "is_location_of",
+                    RelationField::Many(&mut space.is_location_of),
+                ),
+                ("address", RelationField::Many(&mut space.address)),
+                ("included_in", RelationField::Many(&mut space.included_in)),
+                ("has_point", RelationField::Many(&mut space.has_point)),
+            ],
+            Space::Level(space) => vec![
+                ("has_part", RelationField::Many(&mut space.has_part)),
+                ("is_part_of", RelationField::Many(&mut space.is_part_of)),
+                (
+                    "is_location_of",
+                    RelationField::Many(&mut space.is_location_of),
+                ),
+                ("address", RelationField::Many(&mut space.address)),
+                ("included_in", RelationField::Many(&mut space.included_in)),
+                ("has_point", RelationField::Many(&mut space.has_point)),
+            ],
+            Space::Room(space) => vec![
+                ("has_part", RelationField::Many(&mut space.has_part)),
+                ("is_part_of", RelationField::Many(&mut space.is_part_of)),
+                (
+                    "is_location_of",
+                    RelationField::Many(&mut space.is_location_of),
+                ),
+                ("address", RelationField::Many(&mut space.address)),
+                ("included_in", RelationField::Many(&mut space.included_in)),
+                ("has_point", RelationField::Many(&mut space.has_point)),
+            ],
+            Space::Entrance(space) => vec![
+                ("has_part", RelationField::Many(&mut space.has_part)),
+                ("is_part_of", RelationField::Many(&mut space.is_part_of)),
+                (
+                    "is_location_of",
+                    RelationField::Many(&mut space.is_location_of),
+                ),
+                ("address", RelationField::Many(&mut space.address)),
+                ("included_in", RelationField::Many(&mut space.included_in)),
+                ("has_point", RelationField::Many(&mut space.has_point)),
+            ],
+            Space::Desk(space) => vec![
+                ("has_part", RelationField::Many(&mut space.has_part)),
+                ("is_part_of", RelationField::Many(&mut space.is_part_of)),
+                (
+                    "is_location_of",
+                    RelationField::Many(&mut space.is_location_of),
+                ),
+                ("address", RelationField::Many(&mut space.address)),
+                ("included_in", RelationField::Many(&mut space.included_in)),
+                ("has_point", RelationField::Many(&mut space.has_point)),
+            ],
+        }
+    }
 }
 
 #[skip_serializing_none]
@@ -154,6 +478,30 @@ pub struct Entrance {
     pub bookable: Option<bool>,
 }
 
+/// Workspace inside a room or on a level
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Desk {
+    pub id: Uuid,
+    pub name: String,
+    pub identifiers: Option<Vec<Identifier>>,
+    pub has_part: Option<Vec<Relation>>,
+    pub is_part_of: Option<Vec<Relation>>,
+    pub is_location_of: Option<Vec<Relation>>,
+    pub area: Option<Area>,
+    pub capacity: Option<Capacity>,
+    pub address: Option<Vec<Relation>>,
+    pub included_in: Option<Vec<Relation>>,
+    pub has_point: Option<Vec<Relation>>,
+    pub bookable: Option<bool>,
+    pub monitor_count: Option<u32>,
+    pub docking_station: Option<bool>,
+    pub height_adjustable: Option<bool>,
+    /// Other equipment, e.g. `headset` or `webcam`
+    pub equipment: Option<Vec<String>>,
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -230,6 +578,25 @@ pub enum NewSpace {
         has_point: Option<Vec<Relation>>,
         bookable: Option<bool>,
     },
+
+    #[serde(rename_all = "camelCase")]
+    Desk {
+        name: String,
+        identifiers: Option<Vec<Identifier>>,
+        has_part: Option<Vec<Relation>>,
+        is_part_of: Option<Vec<Relation>>,
+        is_location_of: Option<Vec<Relation>>,
+        area: Option<Area>,
+        capacity: Option<Capacity>,
+        address: Option<Vec<Relation>>,
+        included_in: Option<Vec<Relation>>,
+        has_point: Option<Vec<Relation>>,
+        bookable: Option<bool>,
+        monitor_count: Option<u32>,
+        docking_station: Option<bool>,
+        height_adjustable: Option<bool>,
+        equipment: Option<Vec<String>>,
+    },
 }
 
 /// Get spaces of provided type
@@ -280,18 +647,25 @@ impl Request<Space> for Id {
 /// Replace space by id
 impl Request<()> for Space {
     fn endpoint(&self) -> String {
-        let id = match self {
-            Space::AccessControlZone(space) => space.id,
-            Space::Building(space) => space.id,
-            Space::Level(space) => space.id,
-            Space::Room(space) => space.id,
-            Space::Entrance(space) => space.id,
-        };
-
-        format!("space/{}", id)
+        format!("space/{}", self.id())
     }
 
     fn method(&self) -> Method {
         Method::PUT
     }
 }
+
+/// Delete space by id
+impl Request<()> for SpaceId {
+    fn endpoint(&self) -> String {
+        format!("space/{}", self.deref().id)
+    }
+
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn body(&self) -> Option<&Self> {
+        None
+    }
+}
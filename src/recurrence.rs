@@ -0,0 +1,793 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    booking::{self, RoomBooking},
+    error::Error,
+    export,
+    handler::Handler,
+    models::{
+        event::{Booking, Event},
+        Identifier, Relation,
+    },
+};
+
+/// Integration of the identifier linking bookings to their series
+pub const SERIES_INTEGRATION: &str = "series";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl ToString for Frequency {
+    fn to_string(&self) -> String {
+        match self {
+            Frequency::Daily => "DAILY".into(),
+            Frequency::Weekly => "WEEKLY".into(),
+            Frequency::Monthly => "MONTHLY".into(),
+            Frequency::Yearly => "YEARLY".into(),
+        }
+    }
+}
+
+/// RFC 5545 recurrence rule
+///
+/// Supports `FREQ`, `INTERVAL`, `COUNT`, `UNTIL`, `BYDAY` on
+/// weekly rules and `WKST=MO`. Occurrences keep the local time
+/// of day of the series start, monthly and yearly occurrences
+/// its day of month, and months without that day are skipped.
+/// A local time skipped by a daylight saving change is read
+/// with the offset before the change.
+#[derive(SerializeDisplay, DeserializeFromStr, Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    /// Last possible occurrence start, inclusive
+    pub until: Option<DateTime<Utc>>,
+    /// Weekdays of weekly rules, the weekday of the series start if empty
+    pub by_day: Vec<Weekday>,
+}
+
+impl RRule {
+    pub fn new(frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+        }
+    }
+
+    /// Occurrence starts of a series starting at `start`, expanded in UTC
+    pub fn occurrences(&self, start: DateTime<Utc>) -> Occurrences<'_> {
+        self.occurrences_in(start, Tz::UTC)
+    }
+
+    /// Occurrence starts of a series starting at `start`,
+    /// expanded in the local time of `tz`
+    pub fn occurrences_in(&self, start: DateTime<Utc>, tz: Tz) -> Occurrences<'_> {
+        Occurrences {
+            rule: self,
+            start: start.with_timezone(&tz),
+            period: 0,
+            pending: Vec::new(),
+            emitted: 0,
+            done: false,
+        }
+    }
+
+    /// First day of period `period` after the one containing `start`
+    fn period_start(&self, start: DateTime<Tz>, period: u32) -> Option<NaiveDate> {
+        let date = start.date_naive();
+        let step = period.checked_mul(self.interval)?;
+
+        match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(step.into())),
+            Frequency::Weekly => date
+                .week(Weekday::Mon)
+                .first_day()
+                .checked_add_days(Days::new(u64::from(step) * 7)),
+            Frequency::Monthly => date.with_day(1)?.checked_add_months(Months::new(step)),
+            Frequency::Yearly => date
+                .with_day(1)?
+                .checked_add_months(Months::new(step.checked_mul(12)?)),
+        }
+    }
+
+    /// Occurrence starts within period `period`, in order
+    fn candidates(&self, start: DateTime<Tz>, period: u32) -> Option<Vec<DateTime<Utc>>> {
+        let first = self.period_start(start, period)?;
+        let time = start.time();
+
+        let dates = match self.frequency {
+            Frequency::Daily => vec![first],
+            Frequency::Weekly => {
+                let mut days = if self.by_day.is_empty() {
+                    vec![start.weekday()]
+                } else {
+                    self.by_day.clone()
+                };
+                days.sort_by_key(Weekday::num_days_from_monday);
+                days.dedup();
+
+                days.iter()
+                    .filter_map(|day| {
+                        first.checked_add_days(Days::new(day.num_days_from_monday().into()))
+                    })
+                    .collect()
+            }
+            Frequency::Monthly | Frequency::Yearly => {
+                NaiveDate::from_ymd_opt(first.year(), first.month(), start.day())
+                    .into_iter()
+                    .collect()
+            }
+        };
+
+        Some(
+            dates
+                .into_iter()
+                .map(|date| to_utc(&start.timezone(), date.and_time(time)))
+                .collect(),
+        )
+    }
+}
+
+/// UTC time of a local time, the earlier one if the clocks are
+/// set back, and read with the offset before the change if the
+/// clocks are set forward past it, as RFC 5545 does
+pub(crate) fn to_utc(tz: &Tz, time: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&time).earliest() {
+        Some(time) => time.with_timezone(&Utc),
+        None => {
+            let before = tz
+                .offset_from_utc_datetime(&(time - Duration::days(1)))
+                .fix();
+            (time - Duration::seconds(before.local_minus_utc().into())).and_utc()
+        }
+    }
+}
+
+/// Occurrence starts of a rule, endless unless
+/// the rule has a `COUNT` or an `UNTIL`
+pub struct Occurrences<'a> {
+    rule: &'a RRule,
+    start: DateTime<Tz>,
+    period: u32,
+    pending: Vec<DateTime<Utc>>,
+    emitted: u32,
+    done: bool,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = DateTime<Utc>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if self.rule.count.is_some_and(|count| self.emitted >= count) {
+                self.done = true;
+                break;
+            }
+
+            if !self.pending.is_empty() {
+                let next = self.pending.remove(0);
+
+                if next < self.start {
+                    continue;
+                }
+
+                if self.rule.until.is_some_and(|until| next > until) {
+                    self.done = true;
+                    break;
+                }
+
+                self.emitted += 1;
+                return Some(next);
+            }
+
+            match self.rule.candidates(self.start, self.period) {
+                Some(candidates) => {
+                    self.pending = candidates;
+                    self.period += 1;
+                }
+                None => self.done = true,
+            }
+
+            let past_until = self
+                .rule
+                .period_start(self.start, self.period)
+                .zip(self.rule.until)
+                .is_some_and(|(first, until)| {
+                    first > until.with_timezone(&self.start.timezone()).date_naive()
+                });
+
+            if self.pending.is_empty() && past_until {
+                self.done = true;
+            }
+        }
+
+        None
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    Error::InvalidRecurrence(reason.into())
+}
+
+pub(crate) fn parse_weekday(value: &str) -> Result<Weekday, Error> {
+    match value {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(invalid(format!("unsupported BYDAY value {value}"))),
+    }
+}
+
+fn weekday_code(day: &Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl FromStr for RRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+
+        let mut frequency = None;
+        let mut rule = RRule::new(Frequency::Daily);
+
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("malformed part {part}")))?;
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(invalid(format!("unsupported FREQ {value}"))),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| *interval > 0)
+                        .ok_or_else(|| invalid(format!("invalid INTERVAL {value}")))?
+                }
+                "COUNT" => {
+                    rule.count = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("invalid COUNT {value}")))?,
+                    )
+                }
+                "UNTIL" => {
+                    rule.until = Some(
+                        export::parse_ical_datetime(value)
+                            .ok_or_else(|| invalid(format!("invalid UNTIL {value}")))?,
+                    )
+                }
+                "BYDAY" => {
+                    rule.by_day = value
+                        .to_ascii_uppercase()
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?
+                }
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                _ => return Err(invalid(format!("unsupported part {part}"))),
+            }
+        }
+
+        rule.frequency = frequency.ok_or_else(|| invalid("missing FREQ"))?;
+
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(invalid("COUNT and UNTIL are exclusive"));
+        }
+
+        if !rule.by_day.is_empty() && rule.frequency != Frequency::Weekly {
+            return Err(invalid("BYDAY is only supported on weekly rules"));
+        }
+
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.frequency.to_string())?;
+
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", export::ical_datetime(until))?;
+        }
+
+        if !self.by_day.is_empty() {
+            let days: Vec<&str> = self.by_day.iter().map(weekday_code).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Room booked on every occurrence of a recurrence rule
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BookingSeries {
+    /// Identifier shared by the bookings of the series
+    pub uid: String,
+    pub name: String,
+    pub room: Uuid,
+    pub lease: Uuid,
+    pub booked_by: Option<Uuid>,
+    /// Start and end of the first occurrence of the rule
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub rule: RRule,
+    /// Zone the rule is expanded in, so occurrences keep their
+    /// local time across daylight saving changes, UTC if not set
+    pub time_zone: Option<Tz>,
+    /// Starts of cancelled occurrences
+    #[serde(default)]
+    pub exdates: Vec<DateTime<Utc>>,
+    /// Occurrences of the rule starting before this are not part of the series
+    pub from: Option<DateTime<Utc>>,
+    /// Seconds every occurrence is moved by from the start given by the rule
+    #[serde(default)]
+    pub offset: i64,
+}
+
+impl BookingSeries {
+    pub fn new(
+        uid: String,
+        name: String,
+        room: Uuid,
+        lease: Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        rule: RRule,
+    ) -> Self {
+        Self {
+            uid,
+            name,
+            room,
+            lease,
+            booked_by: None,
+            start,
+            end,
+            rule,
+            time_zone: None,
+            exdates: Vec::new(),
+            from: None,
+            offset: 0,
+        }
+    }
+
+    /// Rule starts of the series paired with the actual starts
+    // `Option::is_none_or` needs Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    fn starts(&self) -> impl Iterator<Item = (DateTime<Utc>, DateTime<Utc>)> + '_ {
+        let offset = Duration::seconds(self.offset);
+
+        self.rule
+            .occurrences_in(self.start, self.time_zone.unwrap_or(Tz::UTC))
+            .filter(|start| self.from.map_or(true, |from| *start >= from))
+            .map(move |start| (start, start + offset))
+    }
+
+    /// Start and end of the occurrences starting before `until`
+    pub fn occurrences(&self, until: DateTime<Utc>) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let duration = self.end - self.start;
+
+        self.starts()
+            .map(|(_, start)| start)
+            .take_while(|start| *start < until)
+            .filter(|start| !self.exdates.contains(start))
+            .map(|start| (start, start + duration))
+            .collect()
+    }
+
+    pub fn identifier(&self) -> Identifier {
+        Identifier {
+            integration: SERIES_INTEGRATION.into(),
+            external_id: self.uid.clone(),
+        }
+    }
+
+    /// Whether the booking was made for the series
+    pub fn contains(&self, booking: &Booking) -> bool {
+        booking.identifiers.iter().flatten().any(|identifier| {
+            identifier.integration == SERIES_INTEGRATION && identifier.external_id == self.uid
+        })
+    }
+
+    /// Booking of one occurrence
+    pub fn booking(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> RoomBooking {
+        RoomBooking {
+            name: self.name.clone(),
+            room: self.room,
+            start,
+            end,
+            lease: self.lease,
+            booked_by: self.booked_by,
+            identifiers: Some(vec![self.identifier()]),
+        }
+    }
+
+    /// End the series before the occurrences starting at or after `from`
+    pub fn cancel_from(&mut self, from: DateTime<Utc>) {
+        let last = self
+            .starts()
+            .take_while(|(_, start)| *start < from)
+            .last()
+            .map(|(start, _)| start);
+
+        self.rule.count = None;
+        self.rule.until = Some(last.unwrap_or(self.start - Duration::seconds(1)));
+        self.exdates.retain(|exdate| *exdate < from);
+    }
+
+    /// Move the occurrences starting at or after `from` by `by`
+    /// into a new series, ending this one before them
+    ///
+    /// Returns `None` if no occurrence starts at or after `from`.
+    pub fn shift_from(&mut self, from: DateTime<Utc>, by: Duration) -> Option<BookingSeries> {
+        let (first, _) = self.starts().find(|(_, start)| *start >= from)?;
+
+        let mut next = self.clone();
+        next.uid = format!("{}-{}", self.uid, export::ical_datetime(first));
+        next.from = Some(first);
+        next.offset = self.offset + by.num_seconds();
+        next.exdates = self
+            .exdates
+            .iter()
+            .filter(|exdate| **exdate >= from)
+            .map(|exdate| *exdate + by)
+            .collect();
+
+        self.cancel_from(from);
+
+        Some(next)
+    }
+}
+
+/// Occurrence of a series overlapping other bookings of the room
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OccurrenceConflict {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub bookings: Vec<Uuid>,
+}
+
+/// Bookings made or moved for a series, and the
+/// occurrences left out because of conflicts
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SeriesOutcome {
+    pub booked: Vec<Uuid>,
+    pub conflicts: Vec<OccurrenceConflict>,
+}
+
+/// Book the occurrences of the series starting before `until`
+///
+/// Occurrences overlapping existing bookings are skipped and
+/// reported, the others are booked.
+pub async fn book_series(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    series: &BookingSeries,
+    until: DateTime<Utc>,
+) -> Result<SeriesOutcome, Error> {
+    let space = handler.space(client, token, &series.room).await?;
+
+    if !space.is_bookable() {
+        return Err(Error::NotBookable(series.room));
+    }
+
+    let mut bookings = booking::bookings(handler, client, token).await?;
+    let mut outcome = SeriesOutcome::default();
+
+    for (start, end) in series.occurrences(until) {
+        let clashing: Vec<Uuid> = booking::overlapping(&bookings, &series.room, start, end)
+            .iter()
+            .map(|booking| booking.id)
+            .collect();
+
+        if !clashing.is_empty() {
+            outcome.conflicts.push(OccurrenceConflict {
+                start,
+                end,
+                bookings: clashing,
+            });
+            continue;
+        }
+
+        let id = handler
+            .create_event(client, token, series.booking(start, end).into())
+            .await?;
+
+        bookings.push(Booking {
+            id,
+            name: series.name.clone(),
+            start,
+            end,
+            identifiers: Some(vec![series.identifier()]),
+            booked_by: series.booked_by.map(Relation::from),
+            lease: Some(Relation::from(series.lease)),
+            room: Some(Relation::from(series.room)),
+        });
+        outcome.booked.push(id);
+    }
+
+    Ok(outcome)
+}
+
+/// Delete the bookings of the series starting at or after
+/// `from` and end the series before them
+pub async fn cancel_from(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    series: &mut BookingSeries,
+    from: DateTime<Utc>,
+) -> Result<Vec<Uuid>, Error> {
+    let mut cancelled = Vec::new();
+
+    for booking in booking::bookings(handler, client, token).await? {
+        if series.contains(&booking) && booking.start >= from {
+            handler.delete_event(client, token, &booking.id).await?;
+            cancelled.push(booking.id);
+        }
+    }
+
+    series.cancel_from(from);
+
+    Ok(cancelled)
+}
+
+/// Move the bookings of the series starting at or after `from`
+/// by `by`, splitting them off into the returned series
+///
+/// Bookings that would overlap other bookings of the room are
+/// left in place and reported. Returns `None` if the series has
+/// no occurrence at or after `from`.
+pub async fn shift_from(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    series: &mut BookingSeries,
+    from: DateTime<Utc>,
+    by: Duration,
+) -> Result<Option<(BookingSeries, SeriesOutcome)>, Error> {
+    let (moving, mut others): (Vec<Booking>, Vec<Booking>) =
+        booking::bookings(handler, client, token)
+            .await?
+            .into_iter()
+            .partition(|booking| series.contains(booking) && booking.start >= from);
+
+    let Some(next) = series.shift_from(from, by) else {
+        return Ok(None);
+    };
+
+    let mut moving = moving;
+    moving.sort_by_key(|booking| booking.start);
+
+    let mut outcome = SeriesOutcome::default();
+
+    for mut booking in moving {
+        let (start, end) = (booking.start + by, booking.end + by);
+
+        let clashing: Vec<Uuid> = booking::overlapping(&others, &series.room, start, end)
+            .iter()
+            .map(|booking| booking.id)
+            .collect();
+
+        if !clashing.is_empty() {
+            outcome.conflicts.push(OccurrenceConflict {
+                start,
+                end,
+                bookings: clashing,
+            });
+            others.push(booking);
+            continue;
+        }
+
+        booking.start = start;
+        booking.end = end;
+
+        let mut identifiers = booking.identifiers.take().unwrap_or_default();
+        identifiers.retain(|identifier| identifier.integration != SERIES_INTEGRATION);
+        identifiers.push(next.identifier());
+        booking.identifiers = Some(identifiers);
+
+        handler
+            .replace_event(client, token, Event::Booking(booking.clone()))
+            .await?;
+
+        outcome.booked.push(booking.id);
+        others.push(booking);
+    }
+
+    Ok(Some((next, outcome)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Timelike};
+
+    use super::*;
+
+    fn days(rule: &RRule, start: DateTime<Utc>) -> Vec<String> {
+        rule.occurrences(start)
+            .map(|start| start.format("%m-%d").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn weekly_rule_expands_by_day_and_interval() {
+        let rule: RRule = "RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            rule.to_string(),
+            "FREQ=WEEKLY;INTERVAL=2;COUNT=5;BYDAY=MO,WE"
+        );
+
+        // A Wednesday
+        let start = Utc.with_ymd_and_hms(2024, 1, 3, 9, 0, 0).unwrap();
+        assert_eq!(
+            days(&rule, start),
+            ["01-03", "01-15", "01-17", "01-29", "01-31"]
+        );
+        assert!(rule.occurrences(start).all(|start| start.hour() == 9));
+    }
+
+    #[test]
+    fn monthly_rule_skips_months_without_the_day() {
+        let rule: RRule = "FREQ=MONTHLY;UNTIL=20240601T000000Z".parse().unwrap();
+        let start = Utc.with_ymd_and_hms(2024, 1, 31, 9, 0, 0).unwrap();
+
+        assert_eq!(days(&rule, start), ["01-31", "03-31", "05-31"]);
+    }
+
+    #[test]
+    fn local_time_is_kept_across_daylight_saving() {
+        let rule: RRule = "FREQ=WEEKLY;COUNT=3".parse().unwrap();
+        // 09:00 in Stockholm, the clocks go forward on 31 March
+        let start = Utc.with_ymd_and_hms(2024, 3, 25, 8, 0, 0).unwrap();
+
+        let hours: Vec<u32> = rule
+            .occurrences_in(start, chrono_tz::Europe::Stockholm)
+            .map(|start| start.hour())
+            .collect();
+        assert_eq!(hours, [8, 7, 7]);
+        assert!(rule.occurrences(start).all(|start| start.hour() == 8));
+    }
+
+    #[test]
+    fn skipped_local_time_uses_the_offset_before_the_change() {
+        let rule: RRule = "FREQ=DAILY;COUNT=3".parse().unwrap();
+        // 02:30 in Stockholm, which does not exist on 31 March
+        let start = Utc.with_ymd_and_hms(2024, 3, 30, 1, 30, 0).unwrap();
+
+        let starts: Vec<String> = rule
+            .occurrences_in(start, chrono_tz::Europe::Stockholm)
+            .map(|start| start.format("%d %H:%M").to_string())
+            .collect();
+        assert_eq!(starts, ["30 01:30", "31 01:30", "01 00:30"]);
+    }
+
+    #[test]
+    fn unsupported_rules_are_rejected() {
+        assert!("FREQ=HOURLY".parse::<RRule>().is_err());
+        assert!("FREQ=DAILY;COUNT=1;UNTIL=20240101"
+            .parse::<RRule>()
+            .is_err());
+    }
+
+    #[test]
+    fn rule_round_trips_through_json() {
+        let rule: RRule = "FREQ=DAILY;INTERVAL=3;COUNT=4".parse().unwrap();
+        let json = serde_json::to_string(&rule).unwrap();
+
+        assert_eq!(serde_json::from_str::<RRule>(&json).unwrap(), rule);
+    }
+
+    fn series(count: u32) -> BookingSeries {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+        let rule: RRule = format!("FREQ=WEEKLY;COUNT={count}").parse().unwrap();
+
+        BookingSeries::new(
+            "abc".into(),
+            "Standup".into(),
+            Uuid::from_u128(1),
+            Uuid::from_u128(2),
+            start,
+            start + Duration::hours(1),
+            rule,
+        )
+    }
+
+    #[test]
+    fn exdates_are_left_out() {
+        let mut series = series(6);
+        series.exdates.push(series.start + Duration::weeks(1));
+
+        let occurrences = series.occurrences(series.start + Duration::weeks(100));
+        assert_eq!(occurrences.len(), 5);
+        assert!(occurrences
+            .iter()
+            .all(|(start, end)| *end - *start == Duration::hours(1)));
+    }
+
+    #[test]
+    fn shift_splits_the_series() {
+        let mut series = series(6);
+        let start = series.start;
+        let far = start + Duration::weeks(100);
+
+        let mut next = series
+            .shift_from(start + Duration::weeks(3), Duration::days(1))
+            .unwrap();
+
+        assert_eq!(series.occurrences(far).len(), 3);
+
+        let moved = next.occurrences(far);
+        assert_eq!(moved.len(), 3);
+        assert_eq!(moved[0].0, start + Duration::weeks(3) + Duration::days(1));
+        assert_ne!(next.uid, series.uid);
+
+        next.cancel_from(start + Duration::weeks(5));
+        assert_eq!(next.occurrences(far).len(), 2);
+    }
+
+    #[test]
+    fn shift_past_the_last_occurrence_is_none() {
+        let mut series = series(2);
+
+        assert!(series
+            .shift_from(series.start + Duration::weeks(10), Duration::days(1))
+            .is_none());
+    }
+}
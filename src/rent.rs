@@ -0,0 +1,253 @@
+use chrono::{DateTime, Months, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    export,
+    handler::Handler,
+    models::{
+        event::{Event, Lease},
+        Relation,
+    },
+};
+
+/// Rent due for `period_start <= t < period_end`
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RentPayment {
+    /// Payments are due in advance, at the start of the period
+    pub due: DateTime<Utc>,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    /// Indexation steps applied to the rent
+    pub index_step: u32,
+    /// In minor units of the currency, rounded to the nearest unit
+    pub amount: i64,
+}
+
+/// Rent payments over the term of a lease
+///
+/// Rent is indexed at the start of each payment period, by the
+/// number of whole indexation intervals since the lease start.
+/// A last period cut short by the end of the term is prorated
+/// by time. Amounts are in minor units of the currency, each
+/// payment rounded on its own.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RentSchedule {
+    pub lease: Relation,
+    pub currency: Option<String>,
+    pub payments: Vec<RentPayment>,
+}
+
+impl RentSchedule {
+    /// Load a lease and project its rent up to its end or `until`
+    pub async fn load(
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        lease: &Uuid,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Self, Error> {
+        match handler.event(client, token, lease).await? {
+            Event::Lease(lease) => Self::new(&lease, until),
+            event => Err(Error::InvalidLease(format!(
+                "event {lease} is a {}, not a lease",
+                event.event_type().to_string()
+            ))),
+        }
+    }
+
+    /// Project the rent up to the end of the lease, or `until` if earlier
+    ///
+    /// Fails if the lease has no annual rent, or neither an end nor `until`.
+    pub fn new(lease: &Lease, until: Option<DateTime<Utc>>) -> Result<Self, Error> {
+        let Some(annual_rent) = lease.annual_rent else {
+            return Err(Error::InvalidLease(format!(
+                "lease {} has no annual rent",
+                lease.id
+            )));
+        };
+
+        let end = match (lease.end, until) {
+            (Some(end), Some(until)) => end.min(until),
+            (Some(end), None) => end,
+            (None, Some(until)) => until,
+            (None, None) => {
+                return Err(Error::InvalidLease(format!(
+                    "lease {} has no end",
+                    lease.id
+                )))
+            }
+        };
+
+        let months = lease.payment_frequency.unwrap_or_default().months();
+        let (rate, interval) = lease.indexation.map_or((0., 0), |indexation| {
+            (indexation.rate, indexation.interval_months)
+        });
+
+        let mut payments = Vec::new();
+        let mut elapsed = 0;
+
+        while let Some(period_start) = lease.start.checked_add_months(Months::new(elapsed)) {
+            let Some(full_end) = period_start.checked_add_months(Months::new(months)) else {
+                break;
+            };
+
+            if period_start >= end {
+                break;
+            }
+
+            let period_end = full_end.min(end);
+            let index_step = elapsed.checked_div(interval).unwrap_or(0);
+
+            let full_amount =
+                annual_rent as f64 * f64::from(months) / 12. * (1. + rate).powi(index_step as i32);
+            let share = (period_end - period_start).num_seconds() as f64
+                / (full_end - period_start).num_seconds() as f64;
+
+            payments.push(RentPayment {
+                due: period_start,
+                period_start,
+                period_end,
+                index_step,
+                amount: (full_amount * share).round() as i64,
+            });
+
+            elapsed += months;
+        }
+
+        Ok(Self {
+            lease: Relation::to(&Event::Lease(lease.clone())),
+            currency: lease.currency.clone(),
+            payments,
+        })
+    }
+
+    /// Sum of every payment
+    pub fn total(&self) -> i64 {
+        self.payments.iter().map(|payment| payment.amount).sum()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = export::csv_row(&[
+            "due",
+            "period_start",
+            "period_end",
+            "index_step",
+            "amount",
+            "currency",
+        ]);
+
+        for payment in &self.payments {
+            csv.push_str(&export::csv_row(&[
+                payment.due.to_rfc3339(),
+                payment.period_start.to_rfc3339(),
+                payment.period_end.to_rfc3339(),
+                payment.index_step.to_string(),
+                payment.amount.to_string(),
+                self.currency.clone().unwrap_or_default(),
+            ]));
+        }
+
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn on(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    fn lease(annual_rent: i64, frequency: &str) -> Lease {
+        serde_json::from_value(json!({
+            "id": Uuid::from_u128(1),
+            "name": "Floor lease",
+            "start": on(2024, 1, 1),
+            "end": on(2025, 2, 15),
+            "annualRent": annual_rent,
+            "currency": "SEK",
+            "paymentFrequency": frequency,
+            "indexation": {"rate": 0.1, "intervalMonths": 12},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn indexed_and_prorated_payments() {
+        let schedule = RentSchedule::new(&lease(12_000_000, "quarterly"), None).unwrap();
+
+        let amounts: Vec<_> = schedule
+            .payments
+            .iter()
+            .map(|payment| (payment.index_step, payment.amount))
+            .collect();
+        assert_eq!(
+            amounts,
+            [
+                (0, 3_000_000),
+                (0, 3_000_000),
+                (0, 3_000_000),
+                (0, 3_000_000),
+                (1, 1_650_000),
+            ]
+        );
+        assert_eq!(schedule.payments[4].period_end, on(2025, 2, 15));
+        assert_eq!(schedule.total(), 13_650_000);
+        assert_eq!(schedule.currency.as_deref(), Some("SEK"));
+    }
+
+    #[test]
+    fn amounts_are_rounded_to_minor_units() {
+        let schedule = RentSchedule::new(&lease(100, "monthly"), Some(on(2024, 3, 1))).unwrap();
+
+        let amounts: Vec<_> = schedule
+            .payments
+            .iter()
+            .map(|payment| payment.amount)
+            .collect();
+        assert_eq!(amounts, [8, 8]);
+    }
+
+    #[test]
+    fn until_cuts_the_schedule_short() {
+        let schedule =
+            RentSchedule::new(&lease(12_000_000, "quarterly"), Some(on(2024, 6, 1))).unwrap();
+
+        assert_eq!(schedule.payments.len(), 2);
+        assert_eq!(schedule.payments[1].period_end, on(2024, 6, 1));
+    }
+
+    #[test]
+    fn lease_without_rent_or_end_is_rejected() {
+        let without_rent = Lease {
+            annual_rent: None,
+            ..lease(12_000_000, "quarterly")
+        };
+        assert!(matches!(
+            RentSchedule::new(&without_rent, None),
+            Err(Error::InvalidLease(_))
+        ));
+
+        let open_ended = Lease {
+            end: None,
+            ..lease(12_000_000, "quarterly")
+        };
+        assert!(matches!(
+            RentSchedule::new(&open_ended, None),
+            Err(Error::InvalidLease(_))
+        ));
+    }
+}
@@ -0,0 +1,393 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::space::{Space, SpaceType},
+};
+
+#[derive(Debug, Clone)]
+struct Node {
+    space: Space,
+    parents: Vec<Uuid>,
+    children: Vec<Uuid>,
+}
+
+/// Building → level → room hierarchy linked by
+/// `has_part`/`is_part_of` relations
+///
+/// Relations pointing at spaces that are not part
+/// of the tree are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct SpatialTree {
+    nodes: HashMap<Uuid, Node>,
+}
+
+impl SpatialTree {
+    /// Load spaces of every type and link them
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let mut spaces = Vec::new();
+
+        for space_type in SpaceType::ALL {
+            spaces.extend(handler.spaces(client, token, space_type).await?);
+        }
+
+        Ok(Self::new(spaces))
+    }
+
+    /// Link already fetched spaces
+    pub fn new(spaces: Vec<Space>) -> Self {
+        let spaces: HashMap<Uuid, Space> = spaces
+            .into_iter()
+            .map(|space| (space.id(), space))
+            .collect();
+
+        let mut edges = HashSet::new();
+
+        for (id, space) in &spaces {
+            for part in space.has_part() {
+                edges.insert((*id, part.id));
+            }
+
+            for whole in space.is_part_of() {
+                edges.insert((whole.id, *id));
+            }
+        }
+
+        let mut parents: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+        let mut children: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+
+        for (parent, child) in edges {
+            if parent == child || !spaces.contains_key(&parent) || !spaces.contains_key(&child) {
+                continue;
+            }
+
+            children.entry(parent).or_default().push(child);
+            parents.entry(child).or_default().push(parent);
+        }
+
+        for ids in parents.values_mut().chain(children.values_mut()) {
+            ids.sort_by(|a, b| Self::compare(&spaces[a], &spaces[b]));
+        }
+
+        let nodes = spaces
+            .into_iter()
+            .map(|(id, space)| {
+                let node = Node {
+                    space,
+                    parents: parents.remove(&id).unwrap_or_default(),
+                    children: children.remove(&id).unwrap_or_default(),
+                };
+
+                (id, node)
+            })
+            .collect();
+
+        Self { nodes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Get space by id
+    pub fn get(&self, id: &Uuid) -> Option<&Space> {
+        self.nodes.get(id).map(|node| &node.space)
+    }
+
+    /// All spaces in the tree, in no particular order
+    pub fn spaces(&self) -> impl Iterator<Item = &Space> {
+        self.nodes.values().map(|node| &node.space)
+    }
+
+    /// Spaces that are not part of any other space
+    pub fn roots(&self) -> Vec<&Space> {
+        let mut roots: Vec<&Space> = self
+            .nodes
+            .values()
+            .filter(|node| node.parents.is_empty())
+            .map(|node| &node.space)
+            .collect();
+
+        roots.sort_by(|a, b| Self::compare(a, b));
+
+        roots
+    }
+
+    /// Direct parents of a space
+    pub fn parents(&self, id: &Uuid) -> Vec<&Space> {
+        self.linked(id, |node| &node.parents)
+    }
+
+    /// Direct children of a space, levels ordered by `level_number`
+    pub fn children(&self, id: &Uuid) -> Vec<&Space> {
+        self.linked(id, |node| &node.children)
+    }
+
+    /// All ancestors of a space, nearest first
+    pub fn ancestors(&self, id: &Uuid) -> Vec<&Space> {
+        self.walk(id, |node| &node.parents, true)
+    }
+
+    /// All descendants of a space, depth first
+    pub fn descendants(&self, id: &Uuid) -> Vec<&Space> {
+        self.walk(id, |node| &node.children, false)
+    }
+
+    /// Building a space belongs to, the space
+    /// itself if it is a building
+    pub fn building_of(&self, id: &Uuid) -> Option<&Space> {
+        let space = self.get(id)?;
+
+        if space.space_type() == SpaceType::Building {
+            return Some(space);
+        }
+
+        self.ancestors(id)
+            .into_iter()
+            .find(|space| space.space_type() == SpaceType::Building)
+    }
+
+    /// Levels of a building ordered by `level_number`
+    pub fn levels(&self, building: &Uuid) -> Vec<&Space> {
+        let mut levels: Vec<&Space> = self
+            .descendants(building)
+            .into_iter()
+            .filter(|space| space.space_type() == SpaceType::Level)
+            .collect();
+
+        levels.sort_by(|a, b| Self::compare(a, b));
+
+        levels
+    }
+
+    fn linked(&self, id: &Uuid, edges: impl Fn(&Node) -> &Vec<Uuid>) -> Vec<&Space> {
+        self.nodes
+            .get(id)
+            .map(|node| edges(node).iter().filter_map(|id| self.get(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Breadth or depth first walk that visits every space
+    /// once, so cyclic relations do not loop forever
+    fn walk(
+        &self,
+        id: &Uuid,
+        edges: impl Fn(&Node) -> &Vec<Uuid>,
+        breadth_first: bool,
+    ) -> Vec<&Space> {
+        let mut visited = HashSet::from([*id]);
+        let mut pending: VecDeque<Uuid> = VecDeque::new();
+        let mut res = Vec::new();
+
+        let push = |pending: &mut VecDeque<Uuid>, node: &Node| {
+            if breadth_first {
+                pending.extend(edges(node));
+            } else {
+                for id in edges(node).iter().rev() {
+                    pending.push_front(*id);
+                }
+            }
+        };
+
+        if let Some(node) = self.nodes.get(id) {
+            push(&mut pending, node);
+        }
+
+        while let Some(next) = pending.pop_front() {
+            if !visited.insert(next) {
+                continue;
+            }
+
+            let Some(node) = self.nodes.get(&next) else {
+                continue;
+            };
+
+            res.push(&node.space);
+            push(&mut pending, node);
+        }
+
+        res
+    }
+
+    /// Levels by `level_number`, then everything by name
+    pub(crate) fn compare(a: &Space, b: &Space) -> Ordering {
+        match (a.level_number(), b.level_number()) {
+            (Some(a), Some(b)) if a != b => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            _ => a.name().cmp(b.name()).then_with(|| a.id().cmp(&b.id())),
+        }
+    }
+
+    fn fmt_node(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        id: &Uuid,
+        depth: usize,
+        path: &mut Vec<Uuid>,
+        printed: &mut HashSet<Uuid>,
+    ) -> fmt::Result {
+        let Some(node) = self.nodes.get(id) else {
+            return Ok(());
+        };
+
+        printed.insert(*id);
+
+        writeln!(
+            f,
+            "{}{} ({})",
+            "  ".repeat(depth),
+            node.space.name(),
+            node.space.space_type().to_string()
+        )?;
+
+        if path.contains(id) {
+            return Ok(());
+        }
+
+        path.push(*id);
+
+        for child in &node.children {
+            self.fmt_node(f, child, depth + 1, path, printed)?;
+        }
+
+        path.pop();
+
+        Ok(())
+    }
+}
+
+/// Indented tree, two spaces per level, followed by
+/// spaces only reachable through a cycle
+impl fmt::Display for SpatialTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut printed = HashSet::new();
+
+        for root in self.roots() {
+            self.fmt_node(f, &root.id(), 0, &mut Vec::new(), &mut printed)?;
+        }
+
+        let mut rest: Vec<&Space> = self.spaces().collect();
+        rest.sort_by(|a, b| Self::compare(a, b));
+
+        for space in rest {
+            if !printed.contains(&space.id()) {
+                self.fmt_node(f, &space.id(), 0, &mut Vec::new(), &mut printed)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn space(id: u128, space_type: &str, name: &str, part_of: &[u128]) -> Space {
+        let is_part_of: Vec<_> = part_of
+            .iter()
+            .map(|id| json!({"id": Uuid::from_u128(*id)}))
+            .collect();
+
+        serde_json::from_value(json!({
+            "type": space_type,
+            "id": Uuid::from_u128(id),
+            "name": name,
+            "isPartOf": is_part_of,
+        }))
+        .unwrap()
+    }
+
+    fn ids(spaces: Vec<&Space>) -> Vec<u128> {
+        spaces.iter().map(|space| space.id().as_u128()).collect()
+    }
+
+    #[test]
+    fn ancestors_are_nearest_first() {
+        let tree = SpatialTree::new(vec![
+            space(1, "building", "HQ", &[]),
+            space(2, "level", "Ground", &[1]),
+            space(3, "room", "A", &[2]),
+            space(4, "room", "Z", &[1]),
+            space(5, "room", "Corner", &[3, 4]),
+        ]);
+
+        assert_eq!(ids(tree.ancestors(&Uuid::from_u128(5))), [3, 4, 2, 1]);
+        assert_eq!(ids(tree.descendants(&Uuid::from_u128(1))), [2, 3, 5, 4]);
+        assert_eq!(
+            tree.building_of(&Uuid::from_u128(5)).map(Space::id),
+            Some(Uuid::from_u128(1))
+        );
+    }
+
+    #[test]
+    fn has_part_and_is_part_of_are_merged() {
+        let mut building = space(1, "building", "HQ", &[]);
+        if let Space::Building(building) = &mut building {
+            building.has_part = Some(vec![Uuid::from_u128(2).into()]);
+        }
+
+        let tree = SpatialTree::new(vec![
+            building,
+            space(2, "level", "Ground", &[]),
+            space(3, "room", "A", &[2, 99]),
+        ]);
+
+        assert_eq!(ids(tree.roots()), [1]);
+        assert_eq!(ids(tree.children(&Uuid::from_u128(1))), [2]);
+        assert_eq!(ids(tree.parents(&Uuid::from_u128(3))), [2]);
+    }
+
+    #[test]
+    fn levels_are_ordered_by_number() {
+        let level = |id: u128, name: &str, number: u32| {
+            let mut level = space(id, "level", name, &[1]);
+            if let Space::Level(level) = &mut level {
+                level.level_number = Some(number);
+            }
+            level
+        };
+
+        let tree = SpatialTree::new(vec![
+            space(1, "building", "HQ", &[]),
+            level(2, "Second", 2),
+            level(3, "Ground", 0),
+            space(4, "room", "A", &[3]),
+        ]);
+
+        assert_eq!(ids(tree.levels(&Uuid::from_u128(1))), [3, 2]);
+        assert_eq!(
+            tree.to_string(),
+            "HQ (building)\n  Ground (level)\n    A (room)\n  Second (level)\n"
+        );
+    }
+
+    #[test]
+    fn spaces_in_cycles_are_displayed() {
+        let tree = SpatialTree::new(vec![
+            space(1, "building", "HQ", &[]),
+            space(2, "room", "A", &[3]),
+            space(3, "room", "B", &[2]),
+        ]);
+
+        assert_eq!(
+            tree.to_string(),
+            "HQ (building)\nA (room)\n  B (room)\n    A (room)\n"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::fmt;
+
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{Relation, Resource},
+    tenant::Tenant,
+};
+
+/// Relation whose cached name or type differs from its target
+#[derive(Serialize, Debug, Clone)]
+pub struct StaleRelation {
+    pub field: &'static str,
+    /// Relation as stored on the source entity
+    pub cached: Relation,
+    /// Current type of the target
+    #[serde(rename = "type")]
+    pub rtype: String,
+    /// Current name of the target
+    pub name: String,
+}
+
+/// Entity referencing targets under outdated names
+#[derive(Serialize, Debug, Clone)]
+pub struct StaleEntity {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub rtype: String,
+    pub name: String,
+    pub relations: Vec<StaleRelation>,
+}
+
+/// Every relation in a tenant with an outdated cached name or type
+///
+/// Relations without a cached value and relations to
+/// missing entities are not reported.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct StaleReport {
+    pub entities: Vec<StaleEntity>,
+}
+
+impl StaleReport {
+    /// Compare every relation in `tenant` with its live target
+    pub fn check(tenant: &Tenant) -> Self {
+        let entities = tenant
+            .entities()
+            .iter()
+            .filter_map(|entity| {
+                let relations: Vec<StaleRelation> = entity
+                    .relations()
+                    .into_iter()
+                    .flat_map(|(field, relations)| {
+                        relations.iter().filter_map(move |relation| {
+                            let target = tenant.get(&relation.id)?;
+                            let rtype = target.rtype();
+
+                            let stale_name = relation
+                                .name
+                                .as_deref()
+                                .is_some_and(|name| name != target.name());
+                            let stale_type = relation
+                                .rtype
+                                .as_deref()
+                                .is_some_and(|cached| cached != rtype);
+
+                            (stale_name || stale_type).then(|| StaleRelation {
+                                field,
+                                cached: relation.clone(),
+                                rtype,
+                                name: target.name().to_string(),
+                            })
+                        })
+                    })
+                    .collect();
+
+                (!relations.is_empty()).then(|| StaleEntity {
+                    id: entity.id(),
+                    rtype: entity.rtype(),
+                    name: entity.name().to_string(),
+                    relations,
+                })
+            })
+            .collect();
+
+        Self { entities }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Number of stale relations
+    pub fn len(&self) -> usize {
+        self.entities
+            .iter()
+            .map(|entity| entity.relations.len())
+            .sum()
+    }
+
+    /// Rewrite the cached names and types through the replace
+    /// endpoints, returns the ids of the updated entities
+    ///
+    /// `tenant` must be the snapshot the report was made from.
+    pub async fn refresh(
+        &self,
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        tenant: &Tenant,
+    ) -> Result<Vec<Uuid>, Error> {
+        let mut refreshed = Vec::new();
+
+        for stale in &self.entities {
+            let Some(mut entity) = tenant.get(&stale.id).cloned() else {
+                continue;
+            };
+
+            for (name, mut field) in entity.relation_fields() {
+                for relation in field.relations_mut() {
+                    let Some(current) = stale
+                        .relations
+                        .iter()
+                        .find(|s| s.field == name && s.cached.id == relation.id)
+                    else {
+                        continue;
+                    };
+
+                    relation.rtype = Some(current.rtype.clone());
+                    relation.name = Some(current.name.clone());
+                }
+            }
+
+            tracing::info!(
+                "Refreshing {} relation name(s) on {} {}",
+                stale.relations.len(),
+                stale.rtype,
+                stale.id
+            );
+
+            handler.replace(client, token, entity).await?;
+            refreshed.push(stale.id);
+        }
+
+        Ok(refreshed)
+    }
+}
+
+impl fmt::Display for StaleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entity in &self.entities {
+            writeln!(f, "{} {} ({})", entity.rtype, entity.name, entity.id)?;
+
+            for relation in &entity.relations {
+                writeln!(
+                    f,
+                    "  {}: {} {} -> {} {}",
+                    relation.field,
+                    relation.cached.rtype.as_deref().unwrap_or("-"),
+                    relation.cached.name.as_deref().unwrap_or("-"),
+                    relation.rtype,
+                    relation.name,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::models::{space::Space, Entity};
+
+    fn space(value: Value) -> Entity {
+        serde_json::from_value::<Space>(value).unwrap().into()
+    }
+
+    #[test]
+    fn outdated_names_and_types_are_reported() {
+        let tenant = Tenant::new(vec![
+            space(json!({
+                "type": "building",
+                "id": Uuid::from_u128(1),
+                "name": "Headquarters",
+                "hasPart": [
+                    {"id": Uuid::from_u128(2), "name": "Ground floor", "type": "level"},
+                    {"id": Uuid::from_u128(3), "name": "Lobby", "type": "level"},
+                    {"id": Uuid::from_u128(4)},
+                    {"id": Uuid::from_u128(9), "name": "Gone"},
+                ],
+            })),
+            space(json!({"type": "level", "id": Uuid::from_u128(2), "name": "Ground"})),
+            space(json!({"type": "room", "id": Uuid::from_u128(3), "name": "Lobby"})),
+            space(json!({"type": "room", "id": Uuid::from_u128(4), "name": "Kitchen"})),
+        ]);
+
+        let report = StaleReport::check(&tenant);
+
+        assert_eq!(report.len(), 2);
+        let relations = &report.entities[0].relations;
+        assert_eq!(
+            (relations[0].cached.id, relations[0].name.as_str()),
+            (Uuid::from_u128(2), "Ground")
+        );
+        assert_eq!(
+            (relations[1].cached.id, relations[1].rtype.as_str()),
+            (Uuid::from_u128(3), "room")
+        );
+        assert!(report
+            .to_string()
+            .contains("  has_part: level Ground floor -> level Ground"));
+    }
+}
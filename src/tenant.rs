@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{
+        agent::{Agent, AgentType},
+        asset::{Asset, AssetType},
+        collection::{Collection, CollectionType},
+        event::{Event, EventType},
+        information::{Information, InformationType},
+        point::{Point, PointType},
+        space::{Space, SpaceType},
+        Entity, Resource,
+    },
+};
+
+/// Snapshot of every resource of every kind
+#[derive(Debug, Clone, Default)]
+pub struct Tenant {
+    entities: Vec<Entity>,
+    index: HashMap<Uuid, usize>,
+}
+
+impl Tenant {
+    /// Load all resource kinds
+    ///
+    /// Points are left out, with a warning, when fetching them
+    /// fails, as not every deployment serves or grants `/point`.
+    pub async fn load(handler: &Handler, client: &Client, token: &str) -> Result<Self, Error> {
+        let mut entities: Vec<Entity> = Vec::new();
+
+        for agent_type in AgentType::ALL {
+            let agents = handler.agents(client, token, agent_type).await?;
+            entities.extend(agents.into_iter().map(Entity::from));
+        }
+
+        for asset_type in AssetType::ALL {
+            let assets = handler.assets(client, token, asset_type).await?;
+            entities.extend(assets.into_iter().map(Entity::from));
+        }
+
+        for collection_type in CollectionType::ALL {
+            let collections = handler.collections(client, token, collection_type).await?;
+            entities.extend(collections.into_iter().map(Entity::from));
+        }
+
+        for event_type in EventType::ALL {
+            let events = handler.events(client, token, event_type).await?;
+            entities.extend(events.into_iter().map(Entity::from));
+        }
+
+        for information_type in InformationType::ALL {
+            let information = handler
+                .all_information(client, token, information_type)
+                .await?;
+            entities.extend(information.into_iter().map(Entity::from));
+        }
+
+        for point_type in PointType::ALL {
+            match handler.points(client, token, point_type).await {
+                Ok(points) => entities.extend(points.into_iter().map(Entity::from)),
+                Err(e) => {
+                    tracing::warn!("Skipping {} points: {}", point_type.to_string(), e);
+                }
+            }
+        }
+
+        for space_type in SpaceType::ALL {
+            let spaces = handler.spaces(client, token, space_type).await?;
+            entities.extend(spaces.into_iter().map(Entity::from));
+        }
+
+        Ok(Self::new(entities))
+    }
+
+    /// Build from already fetched entities
+    pub fn new(entities: Vec<Entity>) -> Self {
+        let index = entities
+            .iter()
+            .enumerate()
+            .map(|(i, entity)| (entity.id(), i))
+            .collect();
+
+        Self { entities, index }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn contains(&self, id: &Uuid) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// Get entity by id
+    pub fn get(&self, id: &Uuid) -> Option<&Entity> {
+        self.index.get(id).map(|i| &self.entities[*i])
+    }
+
+    /// All entities in load order
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn agents(&self) -> impl Iterator<Item = &Agent> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Agent(agent) => Some(agent),
+            _ => None,
+        })
+    }
+
+    pub fn assets(&self) -> impl Iterator<Item = &Asset> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Asset(asset) => Some(asset),
+            _ => None,
+        })
+    }
+
+    pub fn collections(&self) -> impl Iterator<Item = &Collection> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Collection(collection) => Some(collection),
+            _ => None,
+        })
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &Event> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Event(event) => Some(event),
+            _ => None,
+        })
+    }
+
+    pub fn information(&self) -> impl Iterator<Item = &Information> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Information(information) => Some(information),
+            _ => None,
+        })
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &Point> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Point(point) => Some(point),
+            _ => None,
+        })
+    }
+
+    pub fn spaces(&self) -> impl Iterator<Item = &Space> {
+        self.entities.iter().filter_map(|entity| match entity {
+            Entity::Space(space) => Some(space),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    availability, booking,
+    error::Error,
+    export,
+    handler::Handler,
+    models::{
+        event::Booking,
+        space::{Space, SpaceType},
+        Relation,
+    },
+};
+
+/// Use of a bookable space over the report period
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpaceUtilisation {
+    pub space: Relation,
+    pub capacity: Option<f64>,
+    pub bookings: usize,
+    pub no_shows: usize,
+    pub booked_hours: f64,
+    /// Hours from check-in to the end or release of the booking
+    pub checked_in_hours: f64,
+    /// `booked_hours` per hour of the period
+    pub booked_ratio: f64,
+    /// `checked_in_hours` per booked hour
+    pub checked_in_ratio: Option<f64>,
+}
+
+/// Booked and checked-in hours of every bookable space
+/// for `from <= t < to`
+///
+/// Bookings count for the part of them within the period. A
+/// booking is a no-show when released without a check-in.
+#[derive(Serialize, Debug, Clone)]
+pub struct UtilisationReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub rows: Vec<SpaceUtilisation>,
+}
+
+/// Hours of `start <= t < end` within `from <= t < to`
+fn hours_within(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> f64 {
+    let seconds = (end.min(to) - start.max(from)).num_seconds().max(0);
+
+    seconds as f64 / 3600.
+}
+
+impl UtilisationReport {
+    /// Load spaces and bookings, then build the report
+    pub async fn load(
+        handler: &Handler,
+        client: &Client,
+        token: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let mut spaces = Vec::new();
+
+        for space_type in SpaceType::ALL {
+            spaces.extend(handler.spaces(client, token, space_type).await?);
+        }
+
+        let bookings = booking::bookings(handler, client, token).await?;
+
+        Ok(Self::new(&spaces, &bookings, from, to))
+    }
+
+    pub fn new(
+        spaces: &[Space],
+        bookings: &[Booking],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Self {
+        let period = hours_within(from, to, from, to);
+
+        let mut by_space: HashMap<Uuid, Vec<&Booking>> = HashMap::new();
+
+        for booking in bookings {
+            if let Some(room) = &booking.room {
+                by_space.entry(room.id).or_default().push(booking);
+            }
+        }
+
+        let mut spaces: Vec<&Space> = spaces.iter().filter(|space| space.is_bookable()).collect();
+        spaces.sort_by(|a, b| a.name().cmp(b.name()).then(a.id().cmp(&b.id())));
+
+        let rows = spaces
+            .into_iter()
+            .map(|space| {
+                let bookings: Vec<&Booking> = by_space
+                    .get(&space.id())
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|booking| availability::overlaps(booking.start, booking.end, from, to))
+                    .collect();
+
+                let booked_hours: f64 = bookings
+                    .iter()
+                    .map(|booking| hours_within(booking.start, booking.end, from, to))
+                    .sum();
+
+                let checked_in_hours: f64 = bookings
+                    .iter()
+                    .filter_map(|booking| {
+                        let checked_in = booking.checked_in_at()?.max(booking.start);
+
+                        Some(hours_within(checked_in, booking.effective_end(), from, to))
+                    })
+                    .sum();
+
+                let no_shows = bookings
+                    .iter()
+                    .filter(|booking| {
+                        booking.released_at().is_some() && booking.checked_in_at().is_none()
+                    })
+                    .count();
+
+                SpaceUtilisation {
+                    space: Relation::to(space),
+                    capacity: availability::capacity(space),
+                    bookings: bookings.len(),
+                    no_shows,
+                    booked_hours,
+                    checked_in_hours,
+                    booked_ratio: if period > 0. {
+                        booked_hours / period
+                    } else {
+                        0.
+                    },
+                    checked_in_ratio: (booked_hours > 0.).then(|| checked_in_hours / booked_hours),
+                }
+            })
+            .collect();
+
+        Self { from, to, rows }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = export::csv_row(&[
+            "space_id",
+            "space_type",
+            "space_name",
+            "capacity",
+            "bookings",
+            "no_shows",
+            "booked_hours",
+            "checked_in_hours",
+            "booked_ratio",
+            "checked_in_ratio",
+        ]);
+
+        for row in &self.rows {
+            csv.push_str(&export::csv_row(&[
+                row.space.id.to_string(),
+                row.space.rtype.clone().unwrap_or_default(),
+                row.space.name.clone().unwrap_or_default(),
+                row.capacity
+                    .map(|capacity| capacity.to_string())
+                    .unwrap_or_default(),
+                row.bookings.to_string(),
+                row.no_shows.to_string(),
+                row.booked_hours.to_string(),
+                row.checked_in_hours.to_string(),
+                row.booked_ratio.to_string(),
+                row.checked_in_ratio
+                    .map(|ratio| ratio.to_string())
+                    .unwrap_or_default(),
+            ]));
+        }
+
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use serde_json::json;
+
+    use super::*;
+
+    fn at(hour: u32, minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, hour, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    fn booking(id: u128, start: DateTime<Utc>, end: DateTime<Utc>) -> Booking {
+        serde_json::from_value(json!({
+            "id": Uuid::from_u128(id),
+            "name": "Planning",
+            "start": start,
+            "end": end,
+            "room": {"id": Uuid::from_u128(1)},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn hours_are_counted_within_the_period() {
+        let spaces: Vec<Space> = vec![serde_json::from_value(json!({
+            "type": "room",
+            "id": Uuid::from_u128(1),
+            "name": "Aurora",
+            "bookable": true,
+        }))
+        .unwrap()];
+
+        let mut checked_in = booking(2, at(9, 0), at(11, 0));
+        checked_in.mark_checked_in(at(9, 30));
+
+        let mut released = booking(3, at(13, 0), at(15, 0));
+        released.mark_released(at(13, 15));
+
+        let ended = booking(4, at(16, 0), at(17, 0));
+        let outside = booking(5, at(19, 0), at(20, 0));
+
+        let report = UtilisationReport::new(
+            &spaces,
+            &[checked_in, released, ended, outside],
+            at(8, 0),
+            at(18, 0),
+        );
+        let row = &report.rows[0];
+
+        assert_eq!(row.bookings, 3);
+        assert_eq!(row.booked_hours, 5.);
+        assert_eq!(row.checked_in_hours, 1.5);
+        assert_eq!(row.no_shows, 1);
+        assert_eq!(row.booked_ratio, 0.5);
+        assert_eq!(row.checked_in_ratio, Some(0.3));
+    }
+}
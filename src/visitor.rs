@@ -0,0 +1,267 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use wrapi::reqwest::Client;
+
+use crate::{
+    error::Error,
+    handler::Handler,
+    models::{
+        agent::{Key, KeyType},
+        event::{Event, EventType, Visit},
+        Identifier,
+    },
+};
+
+/// Integration of the identifiers recording the keys issued for a visit
+pub const KEY_INTEGRATION: &str = "visitor_key";
+
+/// Key valid from the expected arrival to the expected departure
+pub fn visit_key(visit: &Visit, key_type: KeyType, provider: &str, key: &str) -> Key {
+    Key {
+        key_type,
+        provider: provider.into(),
+        key: key.into(),
+        valid_from: Some(visit.expected_arrival),
+        valid_until: Some(visit.expected_departure),
+    }
+}
+
+/// Identifier recording that the key was issued for a visit
+pub fn key_identifier(key: &Key) -> Identifier {
+    Identifier {
+        integration: KEY_INTEGRATION.into(),
+        external_id: format!("{}:{}", key.provider, key.key),
+    }
+}
+
+/// Whether the key was issued for the visit
+pub fn is_visit_key(visit: &Visit, key: &Key) -> bool {
+    let issued = key_identifier(key);
+
+    visit.identifiers.iter().flatten().any(|identifier| {
+        identifier.integration == issued.integration && identifier.external_id == issued.external_id
+    })
+}
+
+fn visitor(visit: &Visit) -> Result<Uuid, Error> {
+    visit
+        .visitor
+        .as_ref()
+        .map(|visitor| visitor.id)
+        .ok_or_else(|| Error::InvalidRelation(format!("visit {} has no visitor", visit.id)))
+}
+
+/// Issue a key to the visitor, valid for the duration of the visit,
+/// and record it on the visit
+///
+/// The key is revoked again if the visit can not be updated.
+pub async fn issue_key(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    visit: &mut Visit,
+    key_type: KeyType,
+    provider: &str,
+    key: &str,
+) -> Result<Key, Error> {
+    let visitor = visitor(visit)?;
+    let key = visit_key(visit, key_type, provider, key);
+
+    handler
+        .create_person_key(client, token, &visitor, key.clone())
+        .await?;
+
+    let mut updated = visit.clone();
+    updated
+        .identifiers
+        .get_or_insert_with(Vec::new)
+        .push(key_identifier(&key));
+
+    if let Err(err) = handler
+        .replace_event(client, token, Event::Visit(updated.clone()))
+        .await
+    {
+        if let Err(rollback) = handler
+            .revoke_person_key(client, token, &visitor, &key)
+            .await
+        {
+            tracing::error!("Failed to revoke unrecorded visitor key: {rollback}");
+        }
+
+        return Err(err);
+    }
+
+    *visit = updated;
+
+    Ok(key)
+}
+
+/// Revoke the keys issued for the visit
+pub async fn revoke_keys(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    visit: &Visit,
+) -> Result<Vec<Key>, Error> {
+    let visitor = visitor(visit)?;
+    let mut revoked = Vec::new();
+
+    for key in handler.person_keys(client, token, &visitor).await? {
+        if !is_visit_key(visit, &key) {
+            continue;
+        }
+
+        handler
+            .revoke_person_key(client, token, &visitor, &key)
+            .await?;
+        revoked.push(key);
+    }
+
+    Ok(revoked)
+}
+
+/// Whether keys issued for the visit are still recorded on it
+pub fn has_keys(visit: &Visit) -> bool {
+    visit
+        .identifiers
+        .iter()
+        .flatten()
+        .any(|identifier| identifier.integration == KEY_INTEGRATION)
+}
+
+/// Drop the issued keys from the visit and record the
+/// departure, keeping an earlier recorded departure
+fn mark_departed(visit: &mut Visit, departed_at: DateTime<Utc>) {
+    if let Some(identifiers) = &mut visit.identifiers {
+        identifiers.retain(|identifier| identifier.integration != KEY_INTEGRATION);
+    }
+
+    visit.departed_at.get_or_insert(departed_at);
+}
+
+/// Revoke the keys of the visit, then store it without them
+/// and with its departure recorded
+async fn close_visit(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    mut visit: Visit,
+    departed_at: DateTime<Utc>,
+) -> Result<Vec<Key>, Error> {
+    let revoked = revoke_keys(handler, client, token, &visit).await?;
+
+    mark_departed(&mut visit, departed_at);
+    handler
+        .replace_event(client, token, Event::Visit(visit))
+        .await?;
+
+    Ok(revoked)
+}
+
+/// Record the departure of the visitor and revoke the keys
+/// issued for the visit
+pub async fn end_visit(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    visit: Visit,
+    departed_at: DateTime<Utc>,
+) -> Result<Vec<Key>, Error> {
+    close_visit(handler, client, token, visit, departed_at).await
+}
+
+/// Close the visits with keys that were expected to have ended
+/// by `now`, returning the ids of the visits closed
+///
+/// The departure of a visit without one recorded is taken to
+/// be its expected departure.
+pub async fn revoke_expired(
+    handler: &Handler,
+    client: &Client,
+    token: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<Uuid>, Error> {
+    let mut visits = Vec::new();
+
+    for event in handler.events(client, token, EventType::Visit).await? {
+        let Event::Visit(visit) = event else {
+            continue;
+        };
+
+        if visit.expected_departure > now || visit.visitor.is_none() || !has_keys(&visit) {
+            continue;
+        }
+
+        let id = visit.id;
+        let departed_at = visit.expected_departure;
+
+        close_visit(handler, client, token, visit, departed_at).await?;
+        visits.push(id);
+    }
+
+    Ok(visits)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap()
+    }
+
+    fn visit(id: u128) -> Visit {
+        serde_json::from_value(json!({
+            "id": Uuid::from_u128(id),
+            "name": "Supplier meeting",
+            "expectedArrival": at(9),
+            "expectedDeparture": at(11),
+            "identifiers": [{"integration": "crm", "externalId": "42"}],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn keys_are_matched_by_the_recorded_identifier() {
+        let mut visit = visit(1);
+        let other = visit.clone();
+        let key = visit_key(&visit, KeyType::Card, "acme", "123");
+
+        assert_eq!(key.valid_from, Some(at(9)));
+        assert_eq!(key.valid_until, Some(at(11)));
+        assert!(!has_keys(&visit));
+
+        visit
+            .identifiers
+            .get_or_insert_with(Vec::new)
+            .push(key_identifier(&key));
+
+        assert!(has_keys(&visit));
+        assert!(is_visit_key(&visit, &key));
+        assert!(!is_visit_key(&other, &key));
+        assert!(!is_visit_key(
+            &visit,
+            &visit_key(&visit, KeyType::Card, "acme", "456")
+        ));
+    }
+
+    #[test]
+    fn departure_drops_the_keys() {
+        let mut visit = visit(1);
+        let key = visit_key(&visit, KeyType::Pin, "acme", "0000");
+        visit
+            .identifiers
+            .get_or_insert_with(Vec::new)
+            .push(key_identifier(&key));
+
+        mark_departed(&mut visit, at(10));
+        mark_departed(&mut visit, at(11));
+
+        assert!(!has_keys(&visit));
+        assert_eq!(visit.identifiers.unwrap().len(), 1);
+        assert_eq!(visit.departed_at, Some(at(10)));
+    }
+}